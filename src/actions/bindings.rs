@@ -0,0 +1,131 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::game_control::GameControl;
+
+const BINDINGS_VERSION: u32 = 1;
+
+/// Keys bound to each [`GameControl`], kept in the platform config directory.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bindings {
+    pub version: u32,
+    pub keys: BTreeMap<GameControl, Vec<KeyCode>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = [
+            (GameControl::Up, vec![KeyCode::W, KeyCode::Up]),
+            (GameControl::Down, vec![KeyCode::S, KeyCode::Down]),
+            (GameControl::Left, vec![KeyCode::A, KeyCode::Left]),
+            (GameControl::Right, vec![KeyCode::D, KeyCode::Right]),
+            (GameControl::ColorSwitch, vec![KeyCode::Space]),
+            (GameControl::LevelReset, vec![KeyCode::R]),
+            (GameControl::Undo, vec![KeyCode::Z, KeyCode::Back]),
+            (GameControl::Redo, vec![KeyCode::Y]),
+            (GameControl::Hint, vec![KeyCode::H]),
+            (GameControl::Pause, vec![KeyCode::Escape]),
+        ];
+        Self {
+            version: BINDINGS_VERSION,
+            keys: keys.into_iter().collect(),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn keys(&self, control: GameControl) -> &[KeyCode] {
+        self.keys.get(&control).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Adds `key` to the keys of `control`, taking it away from any other control.
+    pub fn add(&mut self, control: GameControl, key: KeyCode) {
+        for keys in self.keys.values_mut() {
+            keys.retain(|bound| *bound != key);
+        }
+        self.keys.entry(control).or_default().push(key);
+    }
+
+    /// Unbinds `key` from `control`, leaving its other keys bound.
+    pub fn remove(&mut self, control: GameControl, key: KeyCode) {
+        if let Some(keys) = self.keys.get_mut(&control) {
+            keys.retain(|bound| *bound != key);
+        }
+    }
+
+    /// Label of the first key bound to `control`, for the HUD.
+    pub fn label(&self, control: GameControl) -> String {
+        self.keys(control)
+            .first()
+            .map(|key| key_label(*key))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rgb-doors").join("bindings.json"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else { return Self::default(); };
+        let Ok(contents) = std::fs::read_to_string(&path) else { return Self::default(); };
+        match serde_json::from_str::<Bindings>(&contents) {
+            Ok(mut bindings) if bindings.version == BINDINGS_VERSION => {
+                // Controls added since the file was written get their default keys.
+                for (control, keys) in Self::default().keys {
+                    bindings.keys.entry(control).or_insert(keys);
+                }
+                bindings
+            }
+            Ok(bindings) => {
+                warn!(
+                    "Ignoring bindings with unsupported version {}",
+                    bindings.version
+                );
+                Self::default()
+            }
+            Err(err) => {
+                warn!("Could not read bindings from {:?}: {}", path, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = Self::path() else { return Ok(()); };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        // Write next to the file first so a crash can't leave it half written.
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, contents).map_err(|err| err.to_string())?;
+        std::fs::rename(&tmp_path, &path).map_err(|err| err.to_string())
+    }
+}
+
+pub fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Back => "Bksp".to_string(),
+        KeyCode::Escape => "Esc".to_string(),
+        KeyCode::Return => "Enter".to_string(),
+        key => {
+            let name = format!("{:?}", key);
+            // Digits are named `Key0` to `Key9`.
+            match name.strip_prefix("Key") {
+                Some(digit) if !digit.is_empty() => digit.to_string(),
+                _ => name,
+            }
+        }
+    }
+}
+
+pub fn save_bindings(bindings: Res<Bindings>) {
+    if !bindings.is_changed() || bindings.is_added() {
+        return;
+    }
+    if let Err(err) = bindings.save() {
+        warn!("Could not save bindings: {}", err);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_ecs_ldtk::GridCoords;
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+
+use crate::{
+    levels::CurrentPuzzle,
+    player::{
+        death::Dying,
+        movement::{MovementState, TweenTranslation},
+        Player,
+    },
+    puzzle::{pathfinding::find_path, PuzzleAction, PuzzleState},
+    replay::is_player_busy,
+};
+
+use super::{input_buffer::InputBuffer, Actions, MovementDirection};
+
+/// Path the player is walking after a click or tap. Cleared once walked, when the level
+/// changes under it or when another input comes in.
+#[derive(Resource, Default)]
+pub struct ClickTarget(pub Option<ClickPath>);
+
+pub struct ClickPath {
+    moves: VecDeque<MovementDirection>,
+    /// State the next move starts from. Anything else means undo, a reset or a replay
+    /// moved the player and the path is stale.
+    expected: PuzzleState,
+    /// State the last sent move started from, until the move gets played.
+    sent_from: Option<PuzzleState>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn set_click_target(
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    tile_size_q: Query<&TilemapTileSize>,
+    interaction_q: Query<&Interaction>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut click_target: ResMut<ClickTarget>,
+    mut input_buffer: ResMut<InputBuffer>,
+) {
+    let Ok(window) = window_q.get_single() else { return; };
+    let position = if mouse_input.just_pressed(MouseButton::Left) {
+        window.cursor_position()
+    } else {
+        // Touches start in the top left corner while the cursor starts in the bottom left.
+        touches
+            .iter_just_pressed()
+            .next()
+            .map(|touch| Vec2::new(touch.position().x, window.height() - touch.position().y))
+    };
+    let Some(position) = position else { return; };
+    // The click was meant for a HUD button.
+    if interaction_q
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(puzzle) = puzzle else { return; };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    let Some(ray) = camera.viewport_to_world(camera_transform, position) else { return; };
+    let target = GridCoords {
+        x: (ray.origin.x / tile_size.x).floor() as i32,
+        y: (ray.origin.y / tile_size.y).floor() as i32,
+    };
+    if !puzzle.layout().is_passable(target) {
+        return;
+    }
+    let mut start = puzzle.0.clone();
+    settle_moves(&mut start);
+    // The path is found once, then followed move by move.
+    let Some(moves) = find_path(&start, target) else { return; };
+    click_target.0 = Some(ClickPath {
+        moves: moves.into(),
+        expected: start,
+        sent_from: None,
+    });
+    // Keys pressed before the click would lead somewhere else.
+    input_buffer.clear();
+}
+
+/// Plays out a multi-move the way the puzzle does between inputs.
+fn settle_moves(state: &mut PuzzleState) {
+    while state.is_moving() {
+        state.step(PuzzleAction::Continue);
+    }
+}
+
+/// Sends the next move towards [`ClickTarget`] once the previous one has played out.
+#[allow(clippy::type_complexity)]
+pub fn follow_click_target(
+    mut click_target: ResMut<ClickTarget>,
+    mut actions: EventWriter<Actions>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    player_q: Query<(&MovementState, Option<&TweenTranslation>, Option<&Dying>), With<Player>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if puzzle.is_added() {
+        click_target.0 = None;
+    }
+    let Some(path) = click_target.0.as_mut() else { return; };
+    if puzzle.is_moving() || is_player_busy(&player_q) {
+        return;
+    }
+    // The last move was sent but hasn't been played yet.
+    if path.sent_from.as_ref() == Some(&puzzle.0) {
+        return;
+    }
+    path.sent_from = None;
+    if path.expected != puzzle.0 {
+        click_target.0 = None;
+        return;
+    }
+    let Some(direction) = path.moves.pop_front() else {
+        click_target.0 = None;
+        return;
+    };
+    path.sent_from = Some(path.expected.clone());
+    path.expected.step(PuzzleAction::Move(direction));
+    settle_moves(&mut path.expected);
+    actions.send(Actions {
+        player_movement: Some(direction),
+        ..default()
+    });
+}
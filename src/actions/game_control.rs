@@ -1,6 +1,9 @@
-use bevy::prelude::{Input, KeyCode, Res};
+use bevy::prelude::{GamepadButtonType, Input, KeyCode, Res};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use super::{bindings::Bindings, MovementDirection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum GameControl {
     Up,
     Down,
@@ -8,29 +11,79 @@ pub enum GameControl {
     Right,
     ColorSwitch,
     LevelReset,
+    Undo,
+    Redo,
+    Hint,
+    Pause,
 }
 
 impl GameControl {
+    pub fn all() -> [GameControl; 10] {
+        [
+            GameControl::Up,
+            GameControl::Down,
+            GameControl::Left,
+            GameControl::Right,
+            GameControl::ColorSwitch,
+            GameControl::LevelReset,
+            GameControl::Undo,
+            GameControl::Redo,
+            GameControl::Hint,
+            GameControl::Pause,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameControl::Up => "Up",
+            GameControl::Down => "Down",
+            GameControl::Left => "Left",
+            GameControl::Right => "Right",
+            GameControl::ColorSwitch => "Switch color",
+            GameControl::LevelReset => "Restart level",
+            GameControl::Undo => "Undo",
+            GameControl::Redo => "Redo",
+            GameControl::Hint => "Hint",
+            GameControl::Pause => "Pause",
+        }
+    }
+
+    /// Gamepad button for this control, movement also listens to the left stick.
+    pub fn gamepad_button(&self) -> GamepadButtonType {
+        match self {
+            GameControl::Up => GamepadButtonType::DPadUp,
+            GameControl::Down => GamepadButtonType::DPadDown,
+            GameControl::Left => GamepadButtonType::DPadLeft,
+            GameControl::Right => GamepadButtonType::DPadRight,
+            GameControl::ColorSwitch => GamepadButtonType::South,
+            GameControl::LevelReset => GamepadButtonType::North,
+            GameControl::Undo => GamepadButtonType::West,
+            GameControl::Redo => GamepadButtonType::East,
+            GameControl::Hint => GamepadButtonType::Select,
+            GameControl::Pause => GamepadButtonType::Start,
+        }
+    }
+
     pub fn check_input(
         &self,
+        bindings: &Bindings,
         checker: &dyn Fn(&Res<Input<KeyCode>>, KeyCode) -> bool,
         keyboard_input: &Res<Input<KeyCode>>,
     ) -> bool {
-        match self {
-            GameControl::Up => {
-                checker(keyboard_input, KeyCode::W) || checker(keyboard_input, KeyCode::Up)
-            }
-            GameControl::Down => {
-                checker(keyboard_input, KeyCode::S) || checker(keyboard_input, KeyCode::Down)
-            }
-            GameControl::Left => {
-                checker(keyboard_input, KeyCode::A) || checker(keyboard_input, KeyCode::Left)
-            }
-            GameControl::Right => {
-                checker(keyboard_input, KeyCode::D) || checker(keyboard_input, KeyCode::Right)
-            }
-            GameControl::ColorSwitch => checker(keyboard_input, KeyCode::Space),
-            GameControl::LevelReset => checker(keyboard_input, KeyCode::R),
+        bindings
+            .keys(*self)
+            .iter()
+            .any(|key| checker(keyboard_input, *key))
+    }
+}
+
+impl From<MovementDirection> for GameControl {
+    fn from(direction: MovementDirection) -> Self {
+        match direction {
+            MovementDirection::Up => GameControl::Up,
+            MovementDirection::Down => GameControl::Down,
+            MovementDirection::Left => GameControl::Left,
+            MovementDirection::Right => GameControl::Right,
         }
     }
 }
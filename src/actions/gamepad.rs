@@ -0,0 +1,68 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+
+use super::{game_control::GameControl, MovementDirection};
+
+/// How far the left stick has to be pushed before it counts as a move.
+const STICK_DEADZONE: f32 = 0.5;
+
+/// Buttons and left stick of every connected gamepad.
+#[derive(SystemParam)]
+pub struct GamepadInput<'w, 's> {
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    /// Direction each stick was held in last frame, so holding it only moves once.
+    held_directions: Local<'s, HashMap<Gamepad, MovementDirection>>,
+}
+
+impl<'w, 's> GamepadInput<'w, 's> {
+    pub fn just_pressed(&self, control: GameControl) -> bool {
+        let button_type = control.gamepad_button();
+        self.gamepads.iter().any(|gamepad| {
+            self.buttons
+                .just_pressed(GamepadButton::new(gamepad, button_type))
+        })
+    }
+
+    /// Direction a left stick got pushed in this frame, if any.
+    pub fn stick_just_moved(&mut self) -> Option<MovementDirection> {
+        let mut moved = None;
+        for gamepad in self.gamepads.iter() {
+            let x = self
+                .axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or(0.);
+            let y = self
+                .axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or(0.);
+            let direction = stick_direction(x, y);
+            let previous = match direction {
+                Some(direction) => self.held_directions.insert(gamepad, direction),
+                None => self.held_directions.remove(&gamepad),
+            };
+            if direction.is_some() && direction != previous {
+                moved = moved.or(direction);
+            }
+        }
+        moved
+    }
+}
+
+fn stick_direction(x: f32, y: f32) -> Option<MovementDirection> {
+    if x.abs().max(y.abs()) < STICK_DEADZONE {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0. {
+            MovementDirection::Right
+        } else {
+            MovementDirection::Left
+        })
+    } else {
+        Some(if y > 0. {
+            MovementDirection::Up
+        } else {
+            MovementDirection::Down
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    levels::CurrentPuzzle,
+    player::{
+        death::Dying,
+        movement::{MovementState, TweenTranslation},
+        Player,
+    },
+    puzzle::PuzzleStatus,
+    replay::is_player_busy,
+};
+
+use super::Actions;
+
+pub const DEFAULT_INPUT_BUFFER_DEPTH: usize = 2;
+pub const MAX_INPUT_BUFFER_DEPTH: usize = 5;
+
+/// Input that came in while the player was still moving, sent in order once it can be applied.
+#[derive(Resource)]
+pub struct InputBuffer {
+    /// How many actions may wait at once, `0` drops input during moves.
+    pub depth: usize,
+    queue: VecDeque<Actions>,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_INPUT_BUFFER_DEPTH,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl InputBuffer {
+    pub fn push(&mut self, actions: Actions) {
+        self.queue.push_back(actions);
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// Sends at most one buffered action per frame, so consecutive ones keep their order.
+/// A color switch may go out mid-move, everything else waits until the player is idle.
+/// Hints wait too, so they are worked out from where the player ends up.
+#[allow(clippy::type_complexity)]
+pub fn release_input_buffer(
+    mut input_buffer: ResMut<InputBuffer>,
+    mut actions: EventWriter<Actions>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    player_q: Query<(&MovementState, Option<&TweenTranslation>, Option<&Dying>), With<Player>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if puzzle.is_added() || puzzle.status != PuzzleStatus::Playing {
+        input_buffer.clear();
+        return;
+    }
+    let busy = puzzle.is_moving() || is_player_busy(&player_q);
+    let ready = input_buffer.queue.front().is_some_and(|front| {
+        !busy
+            || (front.player_movement.is_none()
+                && front.undo.is_none()
+                && front.redo.is_none()
+                && front.hint.is_none())
+    });
+    if ready {
+        if let Some(front) = input_buffer.queue.pop_front() {
+            actions.send(front);
+        }
+    }
+    let depth = input_buffer.depth;
+    input_buffer.queue.truncate(depth);
+}
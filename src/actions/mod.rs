@@ -1,22 +1,51 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::actions::bindings::*;
+use crate::actions::click_to_move::*;
 use crate::actions::game_control::*;
+use crate::actions::gamepad::GamepadInput;
+use crate::actions::input_buffer::*;
+use crate::player::movement::next_movement_state;
+use crate::replay::takes_player_input;
 use crate::GameState;
 
-mod game_control;
+pub mod bindings;
+pub mod click_to_move;
+pub mod game_control;
+pub mod gamepad;
+pub mod input_buffer;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
+// This plugin listens for keyboard and gamepad input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<Actions>()
-            .add_system(set_movement_actions.in_set(OnUpdate(GameState::Playing)));
+            .insert_resource(Bindings::load())
+            .init_resource::<ClickTarget>()
+            .init_resource::<InputBuffer>()
+            .add_systems(
+                (
+                    set_movement_actions.run_if(takes_player_input),
+                    release_input_buffer
+                        .run_if(takes_player_input)
+                        .after(set_movement_actions)
+                        .after(next_movement_state),
+                    set_click_target.run_if(takes_player_input),
+                    follow_click_target
+                        .run_if(takes_player_input)
+                        .after(set_movement_actions)
+                        .after(set_click_target),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(save_bindings);
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum MovementDirection {
     Up,
     Down,
@@ -44,50 +73,62 @@ impl MovementDirection {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Actions {
     pub player_movement: Option<MovementDirection>,
     pub color_switch: Option<()>,
     pub level_reset: Option<()>,
+    pub undo: Option<()>,
+    pub redo: Option<()>,
+    pub hint: Option<()>,
 }
 
 pub fn set_movement_actions(
     mut actions: EventWriter<Actions>,
     keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut gamepad_input: GamepadInput,
+    mut click_target: ResMut<ClickTarget>,
+    mut input_buffer: ResMut<InputBuffer>,
 ) {
-    let player_movement = {
-        let directions = [
-            GameControl::Up,
-            GameControl::Down,
-            GameControl::Left,
-            GameControl::Right,
-        ];
-        directions
-            .iter()
-            .find(|&direction| {
-                direction.check_input(&|input, code| input.just_pressed(code), &keyboard_input)
-            })
-            .and_then(|direction| match direction {
-                GameControl::Up => Some(MovementDirection::Up),
-                GameControl::Down => Some(MovementDirection::Down),
-                GameControl::Left => Some(MovementDirection::Left),
-                GameControl::Right => Some(MovementDirection::Right),
-                _ => None,
-            })
+    // Always read the stick so its edge detection sees every frame.
+    let stick_movement = gamepad_input.stick_just_moved();
+    let just_pressed = |control: GameControl| {
+        control.check_input(
+            &bindings,
+            &|input, code| input.just_pressed(code),
+            &keyboard_input,
+        ) || gamepad_input.just_pressed(control)
     };
-    let color_switch = {
-        GameControl::ColorSwitch
-            .check_input(&|input, code| input.just_pressed(code), &keyboard_input)
-    };
-    let level_reset = {
-        GameControl::LevelReset
-            .check_input(&|input, code| input.just_pressed(code), &keyboard_input)
-    };
-    if player_movement.is_some() || color_switch || level_reset {
+    let player_movement = MovementDirection::all()
+        .into_iter()
+        .find(|&direction| just_pressed(GameControl::from(direction)))
+        .or(stick_movement);
+    let color_switch = just_pressed(GameControl::ColorSwitch);
+    let level_reset = just_pressed(GameControl::LevelReset);
+    let undo = just_pressed(GameControl::Undo);
+    let redo = just_pressed(GameControl::Redo);
+    let hint = just_pressed(GameControl::Hint);
+    if player_movement.is_some() || color_switch || level_reset || undo || redo || hint {
+        // Any other input takes over from click to move.
+        click_target.0 = None;
+    }
+    // A reset can't wait for the player to stop and makes buffered input pointless.
+    if level_reset {
+        input_buffer.clear();
         actions.send(Actions {
+            level_reset: Some(()),
+            ..default()
+        });
+    }
+    if player_movement.is_some() || color_switch || undo || redo || hint {
+        input_buffer.push(Actions {
             player_movement,
             color_switch: if color_switch { Some(()) } else { None },
-            level_reset: if level_reset { Some(()) } else { None },
+            level_reset: None,
+            undo: if undo { Some(()) } else { None },
+            redo: if redo { Some(()) } else { None },
+            hint: if hint { Some(()) } else { None },
         });
     }
 }
@@ -11,15 +11,15 @@ impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<AudioConfig>()
             .add_system(start_bgm.in_schedule(OnEnter(GameState::Playing)))
-            .add_systems(
-                (step_audio, mute_control, bgm_mute_control).in_set(OnUpdate(GameState::Playing)),
-            );
+            .add_systems((step_audio, mute_control).in_set(OnUpdate(GameState::Playing)))
+            // Outside of the pausable set, so muting from the pause menu applies right away.
+            .add_system(bgm_mute_control.run_if(in_state(GameState::Playing)));
     }
 }
 
 #[derive(Resource, Default)]
 pub struct AudioConfig {
-    mute: bool,
+    pub mute: bool,
     bgm_handle: Option<Handle<AudioSink>>,
 }
 
@@ -0,0 +1,57 @@
+//! Validates the level pack without opening a window.
+//!
+//! Usage: `cargo run --bin rgb-doors-check [path/to/levels.ldtk]`.
+//! Exits with a non-zero code when any level has issues.
+
+use std::{collections::HashSet, process::ExitCode};
+
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use rgb_doors::puzzle::validation::validate_level;
+
+const DEFAULT_LEVELS_PATH: &str = "assets/levels/levels.ldtk";
+
+fn main() -> ExitCode {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_LEVELS_PATH.to_string());
+    let project = match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            serde_json::from_str::<LdtkJson>(&contents).map_err(|err| err.to_string())
+        }) {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("Could not load {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let level_iids = project
+        .levels
+        .iter()
+        .map(|level| level.iid.as_str())
+        .collect::<HashSet<_>>();
+    let mut failed = false;
+    for level in project.levels.iter() {
+        let report = validate_level(level, &level_iids);
+        let summary = match report.optimal {
+            Some(optimal) => format!("solvable in {} inputs", optimal),
+            None => "not solvable".to_string(),
+        };
+        if report.issues.is_empty() {
+            println!("{}: ok, {}", level.identifier, summary);
+        } else {
+            failed = true;
+            for issue in report.issues {
+                println!("{}: {}", level.identifier, issue);
+            }
+            println!("{}: {}", level.identifier, summary);
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity, LdtkLevel};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{
+    actions::MovementDirection,
+    loading::SpriteAssets,
+    player::movement::TweenTranslation,
+    puzzle::{
+        ldtk::{direction_from_wasd, enum_field, laser_type_from_enum},
+        MirrorOrientation,
+    },
+};
+
+use super::{conveyors::direction_rotation, CurrentPuzzle, RgbEntityAsepriteBundle};
+
+/// Over the floor overlays and the hidden IntGrid layer, under everything on the entities layer.
+const BEAM_Z: f32 = 1.5;
+
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct EmitterSprite;
+
+#[derive(Bundle, LdtkEntity)]
+pub struct EmitterBundle {
+    emitter: EmitterSprite,
+
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+/// A mirror beams bounce off. `index` points into [`PuzzleState::mirrors`] once it is known.
+///
+/// [`PuzzleState::mirrors`]: crate::puzzle::PuzzleState::mirrors
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct MirrorSprite {
+    index: Option<usize>,
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct MirrorBundle {
+    mirror: MirrorSprite,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+/// One tile of a beam, rebuilt every time the puzzle changes.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct BeamSprite;
+
+/// Emitters are drawn facing right and turned to face the way they shoot.
+#[allow(clippy::type_complexity)]
+pub fn spawn_emitters(
+    mut commands: Commands,
+    mut emitter_q: Query<
+        (Entity, &EntityInstance, &mut Transform),
+        (With<EmitterSprite>, Without<AsepriteAnimation>),
+    >,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.beams) else { return; };
+    for (entity, entity_instance, mut transform) in emitter_q.iter_mut() {
+        let fields = &entity_instance.field_instances;
+        let direction = enum_field(fields, "Direction")
+            .and_then(|direction| direction_from_wasd(&direction))
+            .unwrap_or(MovementDirection::Right);
+        let laser_type = enum_field(fields, "Color")
+            .and_then(|color| laser_type_from_enum(&color))
+            .unwrap_or_default();
+        let tag_name = format!("{}_emitter_idle", laser_type.to_string().to_lowercase());
+        let animation = AsepriteAnimation::new(aseprite.info(), &tag_name);
+        transform.rotation = direction_rotation(direction);
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite::new(animation.current_frame()),
+            aseprite: sprites.beams.clone_weak(),
+            animation,
+        });
+    }
+}
+
+pub fn spawn_mirrors(
+    mut commands: Commands,
+    mirror_q: Query<Entity, (With<MirrorSprite>, Without<AsepriteAnimation>)>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.beams) else { return; };
+    for entity in mirror_q.iter() {
+        let animation = AsepriteAnimation::new(aseprite.info(), "mirror_idle");
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite::new(animation.current_frame()),
+            aseprite: sprites.beams.clone_weak(),
+            animation,
+        });
+    }
+}
+
+/// Slides pushed mirrors like crates and flips the sprite, drawn as a `/`, for turned ones.
+pub fn sync_mirrors_with_puzzle(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut mirror_q: Query<(
+        Entity,
+        &mut MirrorSprite,
+        &mut GridCoords,
+        &Transform,
+        &mut TextureAtlasSprite,
+    )>,
+    tile_size_q: Query<&TilemapTileSize>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    for (entity, mut mirror, mut coords, transform, mut sprite) in mirror_q.iter_mut() {
+        // Mirrors are spawned where they start, which tells them apart.
+        if mirror.index.is_none() {
+            mirror.index = puzzle
+                .layout()
+                .mirrors
+                .iter()
+                .position(|start| start.coords == *coords);
+        }
+        let Some(index) = mirror.index else { continue; };
+        let Some(target) = puzzle.mirrors.get(index).copied() else { continue; };
+        let flip_x = target.orientation == MirrorOrientation::Backslash;
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+        if *coords == target.coords {
+            continue;
+        }
+        *coords = target.coords;
+        commands.entity(entity).insert(TweenTranslation {
+            start: transform.translation,
+            end: Vec3::new(
+                target.coords.x as f32 * tile_size.x + tile_size.x / 2.0,
+                target.coords.y as f32 * tile_size.y + tile_size.y / 2.0,
+                transform.translation.z,
+            ),
+            duration: Duration::from_secs_f32(0.2),
+            elapsed: Duration::default(),
+        });
+    }
+}
+
+/// Redraws the beams whenever the puzzle changes, with the same sprites as door lasers.
+/// Like open doors, beams of a color that is lit aren't drawn.
+pub fn sync_beams_with_puzzle(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    beam_q: Query<Entity, With<BeamSprite>>,
+    level_q: Query<Entity, With<Handle<LdtkLevel>>>,
+    tile_size_q: Query<&TilemapTileSize>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    let Some(level) = level_q.iter().next() else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    for entity in beam_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.entity(level).with_children(|parent| {
+        for beam in puzzle.beam_cells() {
+            // The mirror itself shows where the beam turns.
+            if puzzle.mirror_at(beam.coords).is_some() || puzzle.is_laser_open(beam.laser_type) {
+                continue;
+            }
+            let (handle, y_offset) = match beam.direction {
+                MovementDirection::Left | MovementDirection::Right => (&sprites.h_lasers, 0.),
+                MovementDirection::Up | MovementDirection::Down => (&sprites.v_lasers, 8.),
+            };
+            let Some(aseprite) = aseprites.get(handle) else { continue; };
+            let tag_name = format!("{}_laser_idle", beam.laser_type.to_string().to_lowercase());
+            let animation = AsepriteAnimation::new(aseprite.info(), &tag_name);
+            parent.spawn((
+                BeamSprite,
+                beam.coords,
+                RgbEntityAsepriteBundle {
+                    texture_atlas: aseprite.atlas().clone_weak(),
+                    sprite: TextureAtlasSprite::new(animation.current_frame()),
+                    aseprite: handle.clone_weak(),
+                    animation,
+                },
+                SpatialBundle::from_transform(Transform::from_xyz(
+                    beam.coords.x as f32 * tile_size.x + tile_size.x / 2.0,
+                    beam.coords.y as f32 * tile_size.y + tile_size.y / 2.0 + y_offset,
+                    BEAM_Z,
+                )),
+            ));
+        }
+    });
+}
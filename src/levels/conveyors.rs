@@ -0,0 +1,78 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{GridCoords, LdtkLevel};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{actions::MovementDirection, loading::SpriteAssets, puzzle::Cell};
+
+use super::{CurrentPuzzle, RgbEntityAsepriteBundle};
+
+/// Same height as ice, both are drawn over the floor.
+const CONVEYOR_Z: f32 = 0.5;
+
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct ConveyorSprite;
+
+/// Turns a sprite drawn facing right to face `direction`.
+pub fn direction_rotation(direction: MovementDirection) -> Quat {
+    let angle = match direction {
+        MovementDirection::Right => 0.,
+        MovementDirection::Up => FRAC_PI_2,
+        MovementDirection::Left => PI,
+        MovementDirection::Down => -FRAC_PI_2,
+    };
+    Quat::from_rotation_z(angle)
+}
+
+/// Spawns conveyor and one-way gate sprites, both drawn pointing right and turned to face
+/// the direction they carry or let the player through.
+pub fn spawn_conveyors(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    level_q: Query<Entity, With<Handle<LdtkLevel>>>,
+    tile_size_q: Query<&TilemapTileSize>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_added() {
+        return;
+    }
+    let Some(level) = level_q.iter().next() else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    let Some(aseprite) = aseprites.get(&sprites.conveyor) else { return; };
+    let layout = puzzle.layout();
+    commands.entity(level).with_children(|parent| {
+        for y in 0..layout.height {
+            for x in 0..layout.width {
+                let coords = GridCoords { x, y };
+                let (tag_name, direction) = match layout.cell(coords) {
+                    Some(Cell::Conveyor(direction)) => ("conveyor_idle", direction),
+                    Some(Cell::OneWay(direction)) => ("one_way_idle", direction),
+                    _ => continue,
+                };
+                let animation = AsepriteAnimation::new(aseprite.info(), tag_name);
+                parent.spawn((
+                    ConveyorSprite,
+                    coords,
+                    RgbEntityAsepriteBundle {
+                        texture_atlas: aseprite.atlas().clone_weak(),
+                        sprite: TextureAtlasSprite::new(animation.current_frame()),
+                        aseprite: sprites.conveyor.clone_weak(),
+                        animation,
+                    },
+                    SpatialBundle::from_transform(
+                        Transform::from_xyz(
+                            x as f32 * tile_size.x + tile_size.x / 2.0,
+                            y as f32 * tile_size.y + tile_size.y / 2.0,
+                            CONVEYOR_Z,
+                        )
+                        .with_rotation(direction_rotation(direction)),
+                    ),
+                ));
+            }
+        }
+    });
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{GridCoords, LdtkEntity};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{loading::SpriteAssets, player::movement::TweenTranslation};
+
+use super::{CurrentPuzzle, RgbEntityAsepriteBundle};
+
+/// A crate the player can push. `index` points into [`PuzzleState::crates`] once it is known.
+///
+/// [`PuzzleState::crates`]: crate::puzzle::PuzzleState::crates
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct Crate {
+    index: Option<usize>,
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct CrateBundle {
+    pushable: Crate,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+pub fn spawn_crates(
+    mut commands: Commands,
+    crate_q: Query<Entity, (With<Crate>, Without<AsepriteAnimation>)>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.crates) else { return; };
+    for entity in crate_q.iter() {
+        let animation = AsepriteAnimation::new(aseprite.info(), "crate_idle");
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite::new(animation.current_frame()),
+            aseprite: sprites.crates.clone_weak(),
+            animation,
+        });
+    }
+}
+
+/// Slides crates to where the puzzle has them, after a push as well as after an undo.
+pub fn sync_crates_with_puzzle(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut crate_q: Query<(Entity, &mut Crate, &mut GridCoords, &Transform)>,
+    tile_size_q: Query<&TilemapTileSize>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    for (entity, mut pushable, mut coords, transform) in crate_q.iter_mut() {
+        // Crates are spawned where they start, which tells them apart.
+        if pushable.index.is_none() {
+            pushable.index = puzzle
+                .layout()
+                .crates
+                .iter()
+                .position(|start| *start == *coords);
+        }
+        let Some(index) = pushable.index else { continue; };
+        let Some(target) = puzzle.crates.get(index).copied() else { continue; };
+        if *coords == target {
+            continue;
+        }
+        *coords = target;
+        commands.entity(entity).insert(TweenTranslation {
+            start: transform.translation,
+            end: Vec3::new(
+                target.x as f32 * tile_size.x + tile_size.x / 2.0,
+                target.y as f32 * tile_size.y + tile_size.y / 2.0,
+                transform.translation.z,
+            ),
+            duration: Duration::from_secs_f32(0.2),
+            elapsed: Duration::default(),
+        });
+    }
+}
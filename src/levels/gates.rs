@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity};
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{
+    loading::SpriteAssets,
+    puzzle::{ldtk::gate_logic, GateLogic},
+};
+
+use super::{CurrentPuzzle, RgbEntityAsepriteBundle};
+
+const GATE_OFF_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+
+/// A logic gate. `index` points into [`PuzzleLayout::gates`] once it is known.
+///
+/// [`PuzzleLayout::gates`]: crate::puzzle::PuzzleLayout::gates
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct GateSprite {
+    index: Option<usize>,
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct GateBundle {
+    gate: GateSprite,
+
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+pub fn spawn_gates(
+    mut commands: Commands,
+    gate_q: Query<(Entity, &EntityInstance), (With<GateSprite>, Without<AsepriteAnimation>)>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.gates) else { return; };
+    for (entity, entity_instance) in gate_q.iter() {
+        let tag_name = match gate_logic(&entity_instance.field_instances) {
+            GateLogic::And => "and_gate_idle",
+            GateLogic::Or => "or_gate_idle",
+            GateLogic::Not => "not_gate_idle",
+            GateLogic::Xor => "xor_gate_idle",
+        };
+        let animation = AsepriteAnimation::new(aseprite.info(), tag_name);
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite {
+                color: GATE_OFF_COLOR,
+                ..TextureAtlasSprite::new(animation.current_frame())
+            },
+            aseprite: sprites.gates.clone_weak(),
+            animation,
+        });
+    }
+}
+
+/// Lights up gates whose output is on.
+pub fn sync_gates_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut gate_q: Query<(&mut GateSprite, &GridCoords, &mut TextureAtlasSprite)>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    for (mut gate, coords, mut sprite) in gate_q.iter_mut() {
+        // Gates never move, where they stand tells them apart.
+        if gate.index.is_none() {
+            gate.index = puzzle
+                .layout()
+                .gates
+                .iter()
+                .position(|layout_gate| layout_gate.coords == *coords);
+        }
+        let Some(index) = gate.index else { continue; };
+        let color = if puzzle.is_gate_on(index) {
+            Color::WHITE
+        } else {
+            GATE_OFF_COLOR
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    actions::Actions,
+    puzzle::{
+        solver::{solve, Solution},
+        PuzzleAction, PuzzleState, PuzzleStatus,
+    },
+    ui::notifications::Notification,
+};
+
+use super::{CurrentPuzzle, HintCounter};
+
+pub const HINTS_PER_LEVEL: u32 = 3;
+
+/// Sent with the action a hint suggests, so the HUD can point it out.
+pub struct HintShown(pub PuzzleAction);
+
+/// Solution of the state the player asked a hint for. Solving runs on the
+/// [`AsyncComputeTaskPool`], so big levels don't freeze the game while it thinks.
+#[derive(Resource, Default)]
+pub struct HintSolver {
+    /// State the running or last finished solve started from.
+    from: Option<PuzzleState>,
+    result: Arc<Mutex<Option<Option<Solution>>>>,
+    /// Whether the player is still waiting for the result.
+    pending: bool,
+    /// Whether a hint was counted for this result already, showing it again is free.
+    charged: bool,
+}
+
+/// Starts solving the level from where the player stands. Asking again without moving
+/// reuses the last solution, or waits for the one being worked out, and costs no hint.
+pub fn hint_from_action(
+    mut actions: EventReader<Actions>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    hints: Res<HintCounter>,
+    mut solver: ResMut<HintSolver>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    for _ in actions.iter().filter(|action| action.hint.is_some()) {
+        if puzzle.status != PuzzleStatus::Playing {
+            continue;
+        }
+        let already_solving = solver.from.as_ref() == Some(&puzzle.0);
+        if hints.used >= HINTS_PER_LEVEL && !(already_solving && solver.charged) {
+            notifications.send(Notification::new(
+                "No hints left for this level".to_string(),
+            ));
+            continue;
+        }
+        solver.pending = true;
+        if already_solving {
+            continue;
+        }
+        let state = puzzle.0.clone();
+        let result = Arc::new(Mutex::new(None));
+        let task_result = result.clone();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let solution = solve(&state);
+                *task_result.lock().unwrap() = Some(solution);
+            })
+            .detach();
+        solver.from = Some(puzzle.0.clone());
+        solver.result = result;
+        solver.charged = false;
+    }
+}
+
+/// Tells the player the first turn of the solution once it is found. Dropped if the player
+/// moved on in the meantime.
+pub fn show_hint(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut hints: ResMut<HintCounter>,
+    mut solver: ResMut<HintSolver>,
+    mut notifications: EventWriter<Notification>,
+    mut hint_shown: EventWriter<HintShown>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !solver.pending {
+        return;
+    }
+    let result = solver.result.lock().unwrap().clone();
+    let Some(solution) = result else { return; };
+    solver.pending = false;
+    if solver.from.as_ref() != Some(&puzzle.0) {
+        return;
+    }
+    let first_turn = solution.and_then(|solution| describe_first_turn(&puzzle.0, &solution));
+    let Some((first_action, text)) = first_turn else {
+        notifications.send(Notification::new(
+            "The level can't be finished from here, undo or restart".to_string(),
+        ));
+        return;
+    };
+    if !solver.charged {
+        hints.used += 1;
+        solver.charged = true;
+    }
+    notifications.send(Notification::new(format!(
+        "{} ({} hints left)",
+        text,
+        HINTS_PER_LEVEL - hints.used
+    )));
+    hint_shown.send(HintShown(first_action));
+}
+
+/// Describes the first input of `solution` and the color switches it makes before the move
+/// started by that input comes to rest, since those can't be hinted once the player is moving.
+fn describe_first_turn(
+    puzzle: &PuzzleState,
+    solution: &Solution,
+) -> Option<(PuzzleAction, String)> {
+    let mut state = puzzle.clone();
+    let mut actions = solution
+        .actions
+        .iter()
+        .copied()
+        .skip_while(|action| *action == PuzzleAction::Continue);
+    let first_action = actions.next()?;
+    state.step(first_action);
+    let mut text = match first_action {
+        PuzzleAction::Move(direction) => format!("Move {:?}", direction),
+        _ => format!("Switch to {:?}", state.color),
+    };
+    let mut tiles = 1;
+    while state.is_moving() {
+        let Some(action) = actions.next() else { break; };
+        state.step(action);
+        match action {
+            PuzzleAction::Continue => tiles += 1,
+            PuzzleAction::SwitchColor => text.push_str(&format!(
+                ", then switch to {:?} after {} {}",
+                state.color,
+                tiles,
+                if tiles == 1 { "tile" } else { "tiles" }
+            )),
+            PuzzleAction::Move(_) => break,
+        }
+    }
+    Some((first_action, text))
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{GridCoords, LdtkLevel};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{loading::SpriteAssets, puzzle::Cell};
+
+use super::{CurrentPuzzle, RgbEntityAsepriteBundle};
+
+/// Above the floor tiles, below the hidden IntGrid layer and everything standing on the ice.
+const ICE_Z: f32 = 0.5;
+
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct IceSprite;
+
+/// The IntGrid layer is hidden, so ice gets its own sprites on top of the level's tiles.
+pub fn spawn_ice(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    level_q: Query<Entity, With<Handle<LdtkLevel>>>,
+    tile_size_q: Query<&TilemapTileSize>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_added() {
+        return;
+    }
+    let Some(level) = level_q.iter().next() else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    let Some(aseprite) = aseprites.get(&sprites.ice) else { return; };
+    let layout = puzzle.layout();
+    commands.entity(level).with_children(|parent| {
+        for y in 0..layout.height {
+            for x in 0..layout.width {
+                let coords = GridCoords { x, y };
+                if layout.cell(coords) != Some(Cell::Ice) {
+                    continue;
+                }
+                let animation = AsepriteAnimation::new(aseprite.info(), "ice_idle");
+                parent.spawn((
+                    IceSprite,
+                    coords,
+                    RgbEntityAsepriteBundle {
+                        texture_atlas: aseprite.atlas().clone_weak(),
+                        sprite: TextureAtlasSprite::new(animation.current_frame()),
+                        aseprite: sprites.ice.clone_weak(),
+                        animation,
+                    },
+                    SpatialBundle::from_transform(Transform::from_xyz(
+                        x as f32 * tile_size.x + tile_size.x / 2.0,
+                        y as f32 * tile_size.y + tile_size.y / 2.0,
+                        ICE_Z,
+                    )),
+                ));
+            }
+        }
+    });
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity, LdtkLevel};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{
+    loading::SpriteAssets,
+    player::color_control::ColorControl,
+    puzzle::{
+        ldtk::{color_from_enum, enum_field},
+        Cell,
+    },
+    ui::notifications::Notification,
+};
+
+use super::{CurrentPuzzle, PuzzleStepped, RgbEntityAsepriteBundle};
+
+/// Locks sit with the other floor overlays, below the hidden IntGrid layer.
+const LOCK_Z: f32 = 0.5;
+
+/// A key lying in the level. `index` points into [`PuzzleState::keys_taken`] once it is known.
+///
+/// [`PuzzleState::keys_taken`]: crate::puzzle::PuzzleState::keys_taken
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct KeySprite {
+    index: Option<usize>,
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct KeyBundle {
+    key: KeySprite,
+
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct LockSprite;
+
+fn color_name(color: ColorControl) -> &'static str {
+    match color {
+        ColorControl::Red => "red",
+        ColorControl::Green => "green",
+        ColorControl::Blue => "blue",
+    }
+}
+
+pub fn spawn_keys(
+    mut commands: Commands,
+    key_q: Query<(Entity, &EntityInstance), (With<KeySprite>, Without<AsepriteAnimation>)>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.keys) else { return; };
+    for (entity, entity_instance) in key_q.iter() {
+        let color = enum_field(&entity_instance.field_instances, "Color")
+            .and_then(|color| color_from_enum(&color))
+            .unwrap_or_default();
+        let tag_name = format!("{}_key_idle", color_name(color));
+        let animation = AsepriteAnimation::new(aseprite.info(), &tag_name);
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite::new(animation.current_frame()),
+            aseprite: sprites.keys.clone_weak(),
+            animation,
+        });
+    }
+}
+
+/// The IntGrid layer is hidden, so locks get their own sprites on top of the level's tiles.
+pub fn spawn_locks(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    level_q: Query<Entity, With<Handle<LdtkLevel>>>,
+    tile_size_q: Query<&TilemapTileSize>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_added() {
+        return;
+    }
+    let Some(level) = level_q.iter().next() else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    let Some(aseprite) = aseprites.get(&sprites.keys) else { return; };
+    let layout = puzzle.layout();
+    commands.entity(level).with_children(|parent| {
+        for y in 0..layout.height {
+            for x in 0..layout.width {
+                let coords = GridCoords { x, y };
+                let Some(Cell::Lock(color)) = layout.cell(coords) else { continue; };
+                let tag_name = format!("{}_lock_idle", color_name(color));
+                let animation = AsepriteAnimation::new(aseprite.info(), &tag_name);
+                parent.spawn((
+                    LockSprite,
+                    coords,
+                    RgbEntityAsepriteBundle {
+                        texture_atlas: aseprite.atlas().clone_weak(),
+                        sprite: TextureAtlasSprite::new(animation.current_frame()),
+                        aseprite: sprites.keys.clone_weak(),
+                        animation,
+                    },
+                    SpatialBundle::from_transform(Transform::from_xyz(
+                        x as f32 * tile_size.x + tile_size.x / 2.0,
+                        y as f32 * tile_size.y + tile_size.y / 2.0,
+                        LOCK_Z,
+                    )),
+                ));
+            }
+        }
+    });
+}
+
+/// Hides taken keys and opened locks, and shows them again after an undo.
+#[allow(clippy::type_complexity)]
+pub fn sync_keys_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut key_q: Query<(&mut KeySprite, &GridCoords, &mut Visibility), Without<LockSprite>>,
+    mut lock_q: Query<(&GridCoords, &mut Visibility), (With<LockSprite>, Without<KeySprite>)>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    for (mut key, coords, visibility) in key_q.iter_mut() {
+        // Keys never move, where they lie tells them apart.
+        if key.index.is_none() {
+            key.index = puzzle.layout().key_at(*coords);
+        }
+        let Some(index) = key.index else { continue; };
+        let taken = puzzle.keys_taken.get(index).copied().unwrap_or(false);
+        set_hidden(visibility, taken);
+    }
+    for (coords, visibility) in lock_q.iter_mut() {
+        set_hidden(visibility, puzzle.unlocked.contains(coords));
+    }
+}
+
+fn set_hidden(mut visibility: Mut<Visibility>, hidden: bool) {
+    let next = if hidden {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if *visibility != next {
+        *visibility = next;
+    }
+}
+
+pub fn notify_keys(
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    for PuzzleStepped(outcome) in puzzle_stepped.iter() {
+        let unlocked = outcome
+            .unlocked
+            .and_then(|coords| puzzle.layout().cell(coords));
+        if let Some(Cell::Lock(color)) = unlocked {
+            notifications.send(Notification::new(format!(
+                "Used the {} key",
+                color_name(color)
+            )));
+        }
+        if let Some(key) = outcome
+            .picked_up_key
+            .and_then(|index| puzzle.layout().keys.get(index))
+        {
+            notifications.send(Notification::new(format!(
+                "Picked up a {} key",
+                color_name(key.color)
+            )));
+        }
+    }
+}
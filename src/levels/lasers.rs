@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 use bevy_ecs_ldtk::{prelude::FieldValue::Enum, EntityInstance, GridCoords, LdtkEntity};
 use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
 
-use crate::loading::SpriteAssets;
+use crate::{loading::SpriteAssets, puzzle::ldtk::laser_type_from_enum};
 
 use super::{
     tiles::{Laser, LaserType},
@@ -76,21 +78,11 @@ fn laser_aseprite_bundle(
         Enum(value) => value.to_owned(),
         _ => None,
     }?;
-    let tag_name = match color_value.as_str() {
-        "Red" => "red_laser_idle".into(),
-        "Green" => "green_laser_idle".into(),
-        "Blue" => "blue_laser_idle".into(),
-        _ => None,
-    }?;
-    let actual_laser_sprite = match color_value.as_str() {
-        "Red" => Some(LaserSprite(LaserType::Red)),
-        "Green" => Some(LaserSprite(LaserType::Green)),
-        "Blue" => Some(LaserSprite(LaserType::Blue)),
-        _ => None,
-    }?;
-    *laser_sprite = actual_laser_sprite;
+    let laser_type = laser_type_from_enum(&color_value)?;
+    let tag_name = format!("{}_laser_idle", laser_type.to_string().to_lowercase());
+    *laser_sprite = LaserSprite(laser_type);
     let aseprite = aseprites.get(&sprite)?;
-    let laser_animation = AsepriteAnimation::new(aseprite.info(), tag_name);
+    let laser_animation = AsepriteAnimation::new(aseprite.info(), &tag_name);
     (
         RgbEntityAsepriteBundle {
             texture_atlas: aseprite.atlas().clone_weak(),
@@ -103,22 +95,81 @@ fn laser_aseprite_bundle(
         .into()
 }
 
+/// Fade of a laser sprite that is switching on or off.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LaserFade {
+    elapsed: Duration,
+    closing: bool,
+}
+
+const LASER_FADE_DURATION: Duration = Duration::from_millis(150);
+
+/// Fades laser sprites in when their laser closes and out when it opens. Sprites that
+/// haven't been drawn yet just switch.
 #[allow(clippy::type_complexity)]
 pub fn laser_visibility(
-    mut laser_sprite_q: Query<(&mut Visibility, &GridCoords), With<LaserSprite>>,
+    mut commands: Commands,
+    mut laser_sprite_q: Query<
+        (
+            Entity,
+            &mut Visibility,
+            &GridCoords,
+            Option<&mut TextureAtlasSprite>,
+            Option<&mut LaserFade>,
+        ),
+        With<LaserSprite>,
+    >,
     laser_q: Query<(&Laser, &GridCoords), Changed<Laser>>,
+    time: Res<Time>,
 ) {
-    for (mut laser_visibility, sprite_coords) in laser_sprite_q.iter_mut() {
-        let visible = laser_q
+    for (entity, mut laser_visibility, sprite_coords, sprite, fade) in laser_sprite_q.iter_mut() {
+        let closing = laser_q
             .iter()
             .find(|(_, laser_coords)| **laser_coords == *sprite_coords)
             .map(|(laser, _)| !laser.is_open);
-        if let Some(visible) = visible {
-            *laser_visibility = if visible {
-                Visibility::Visible
-            } else {
-                Visibility::Hidden
-            };
+        let Some(mut sprite) = sprite else {
+            if let Some(closing) = closing {
+                *laser_visibility = if closing {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                };
+            }
+            continue;
+        };
+        let mut fade = match (closing, fade) {
+            (Some(closing), Some(mut fade)) => {
+                // Turning around halfway keeps the alpha where it is.
+                if fade.closing != closing {
+                    fade.elapsed = LASER_FADE_DURATION.saturating_sub(fade.elapsed);
+                    fade.closing = closing;
+                }
+                fade
+            }
+            (Some(closing), None) => {
+                if closing == (*laser_visibility != Visibility::Hidden) {
+                    continue;
+                }
+                commands.entity(entity).insert(LaserFade {
+                    elapsed: Duration::ZERO,
+                    closing,
+                });
+                *laser_visibility = Visibility::Visible;
+                sprite.color.set_a(if closing { 0. } else { 1. });
+                continue;
+            }
+            (None, Some(fade)) => fade,
+            (None, None) => continue,
         };
+        fade.elapsed += time.delta();
+        let t = (fade.elapsed.as_secs_f32() / LASER_FADE_DURATION.as_secs_f32()).min(1.);
+        sprite.color.set_a(if fade.closing { t } else { 1. - t });
+        if t >= 1. {
+            sprite.color.set_a(1.);
+            if !fade.closing {
+                *laser_visibility = Visibility::Hidden;
+            }
+            commands.entity(entity).remove::<LaserFade>();
+        }
     }
 }
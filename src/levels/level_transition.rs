@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use bevy::prelude::*;
-use bevy_ecs_ldtk::{prelude::FieldValue, *};
+use bevy_ecs_ldtk::*;
 use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
 
 use crate::{
@@ -13,10 +13,11 @@ use crate::{
         movement_effects::{MovementSideEffects, SideEffect},
         Player,
     },
+    puzzle::ldtk::finish_from_entity_instance,
     ui::{bg_color_tween::BackgroundColorTween, notifications::Notification, LevelScreen},
 };
 
-use super::RgbEntityAsepriteBundle;
+use super::{CurrentPuzzle, HintCounter, MoveCounter, PuzzleStepped, RgbEntityAsepriteBundle};
 
 #[derive(Component, Debug, Default)]
 pub struct Finish {
@@ -41,29 +42,22 @@ pub struct FinishBundle {
 pub fn spawn_finish(
     mut commands: Commands,
     mut finish_query: Query<
-        (Entity, &EntityInstance, &mut Finish, &mut Transform),
+        (
+            Entity,
+            &EntityInstance,
+            &GridCoords,
+            &mut Finish,
+            &mut Transform,
+        ),
         Without<AsepriteAnimation>,
     >,
     sprites: Res<SpriteAssets>,
     aseprites: Res<Assets<Aseprite>>,
 ) {
-    for (entity, entity_instance, mut finish, mut transform) in finish_query.iter_mut() {
-        finish.next_level = entity_instance
-            .field_instances
-            .iter()
-            .find(|field_instance| field_instance.identifier == "NextLevel")
-            .and_then(|field_instance| match &field_instance.value {
-                FieldValue::String(Some(value)) => Some(LevelSelection::Iid(value.to_owned())),
-                _ => None,
-            });
-        finish.message = entity_instance
-            .field_instances
-            .iter()
-            .find(|field_instance| field_instance.identifier == "Message")
-            .and_then(|field_instance| match &field_instance.value {
-                FieldValue::String(Some(value)) => Some(Notification::new(value.to_owned())),
-                _ => None,
-            });
+    for (entity, entity_instance, coords, mut finish, mut transform) in finish_query.iter_mut() {
+        let finish_def = finish_from_entity_instance(entity_instance, *coords);
+        finish.next_level = finish_def.next_level.map(LevelSelection::Iid);
+        finish.message = finish_def.message.map(Notification::new);
         transform.translation.y += 8.0;
         if let Some(aseprite_bundle) = finish_sprite(&sprites, &aseprites) {
             commands.entity(entity).insert(aseprite_bundle);
@@ -87,28 +81,34 @@ fn finish_sprite(
     })
 }
 
-#[allow(clippy::type_complexity)]
 pub fn finish_system(
-    mut player_q: Query<
-        (&GridCoords, &mut MovementSideEffects),
-        (With<Player>, Changed<GridCoords>, Without<OnFinish>),
-    >,
-    mut finish_query: Query<(&Finish, &GridCoords)>,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    moves: Res<MoveCounter>,
+    hints: Res<HintCounter>,
+    finish_query: Query<(&Finish, &GridCoords)>,
     mut notifications: EventWriter<Notification>,
     mut level_transition: EventWriter<LevelTransition>,
 ) {
-    for (player_grid_coords, mut forbid_movement) in player_q.iter_mut() {
-        for (finish, grid_coords) in finish_query.iter_mut() {
-            if player_grid_coords == grid_coords {
+    let Some(puzzle) = puzzle else { return; };
+    for PuzzleStepped(outcome) in puzzle_stepped.iter() {
+        let Some(finish_index) = outcome.finished else { continue; };
+        let result = match puzzle.layout().par {
+            Some(par) => format!("Moves / par: {} / {}", **moves, par),
+            None => format!("Moves: {}", **moves),
+        };
+        notifications.send(Notification::new(match hints.used {
+            0 => result,
+            used => format!("{}, hints: {}", result, used),
+        }));
+        let finish_coords = puzzle.layout().finishes[finish_index].coords;
+        for (finish, grid_coords) in finish_query.iter() {
+            if *grid_coords == finish_coords {
                 if let Some(message) = &finish.message {
                     notifications.send(message.clone());
                 }
                 if finish.next_level.is_some() {
                     level_transition.send(LevelTransition);
-                } else {
-                    for movement_direction in MovementDirection::all() {
-                        forbid_movement.set(movement_direction, SideEffect::DisabledMovement);
-                    }
                 }
             }
         }
@@ -1,13 +1,21 @@
+pub mod beams;
 mod camera_fit;
+pub mod conveyors;
+pub mod crates;
+pub mod gates;
+pub mod hints;
+pub mod ice;
+pub mod keys;
 pub mod lasers;
 mod level_transition;
 pub mod panel;
 pub mod reset;
+pub mod teleporters;
 pub mod tiles;
 
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
 use bevy_ecs_ldtk::{
     prelude::{FieldValue, LdtkEntityAppExt, LdtkIntCellAppExt},
     *,
@@ -16,18 +24,30 @@ use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
 
 use crate::{
     loading::LevelAssets,
+    puzzle::{history::History, solver::solve, PuzzleState, StepOutcome},
     ui::notifications::{CleanNotificationQueue, Notification},
     GameState,
 };
 
 use self::{
+    beams::{
+        spawn_emitters, spawn_mirrors, sync_beams_with_puzzle, sync_mirrors_with_puzzle,
+        EmitterBundle, MirrorBundle,
+    },
     camera_fit::camera_fit_inside_current_level,
+    conveyors::spawn_conveyors,
+    crates::{spawn_crates, sync_crates_with_puzzle, CrateBundle},
+    gates::{spawn_gates, sync_gates_with_puzzle, GateBundle},
+    hints::{hint_from_action, show_hint, HintShown, HintSolver},
+    ice::spawn_ice,
+    keys::{notify_keys, spawn_keys, spawn_locks, sync_keys_with_puzzle, KeyBundle},
     lasers::{laser_visibility, spawn_lasers, LaserBundle},
     level_transition::{
         finish_system, level_transition, spawn_finish, FinishBundle, LevelTransition,
     },
-    panel::{setup_panel, step_on_panel, PanelBundle},
+    panel::{setup_panel, step_on_panel, sync_panels_with_puzzle, PanelBundle},
     reset::{reset_level, respawn_on_death, respawn_on_level_reset, ResetLevelEvent},
+    teleporters::{spawn_teleporters, TeleporterBundle},
     tiles::WallBundle,
     tiles::{DoorBundle, FloorBundle},
 };
@@ -44,6 +64,36 @@ pub struct RgbEntityAsepriteBundle {
     pub texture_atlas: Handle<TextureAtlas>,
 }
 
+/// Rules state of the level currently on screen.
+#[derive(Resource, Deref, DerefMut)]
+pub struct CurrentPuzzle(pub PuzzleState);
+
+/// Moves and color switches the player made in the current level.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
+pub struct MoveCounter(pub u32);
+
+/// Hints the player asked for in a level. Kept when the level is restarted, so restarting
+/// doesn't hand out new ones.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct HintCounter {
+    pub level_iid: Option<String>,
+    pub used: u32,
+}
+
+/// What undo and redo rewind to.
+#[derive(Clone)]
+pub struct TurnSnapshot {
+    pub puzzle: PuzzleState,
+    pub moves: MoveCounter,
+}
+
+/// Turn snapshots for undo and redo. Starts empty with every level.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PuzzleHistory(pub History<TurnSnapshot>);
+
+/// Sent every time [`CurrentPuzzle`] is stepped, so systems can present what happened.
+pub struct PuzzleStepped(pub StepOutcome);
+
 impl Plugin for LevelsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(LdtkPlugin)
@@ -54,17 +104,35 @@ impl Plugin for LevelsPlugin {
             .insert_resource(LevelSelection::Index(self.level_index))
             .add_event::<LevelTransition>()
             .add_event::<ResetLevelEvent>()
+            .add_event::<PuzzleStepped>()
+            .add_event::<HintShown>()
+            .init_resource::<PuzzleHistory>()
+            .init_resource::<MoveCounter>()
+            .init_resource::<HintCounter>()
+            .init_resource::<HintSolver>()
             .register_ldtk_int_cell_for_layer::<WallBundle>("IntGrid", 1)
             .register_ldtk_int_cell_for_layer::<FloorBundle>("IntGrid", 2)
             .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 3)
             .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 4)
             .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 5)
+            .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 6)
+            .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 7)
+            .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 8)
+            .register_ldtk_int_cell_for_layer::<DoorBundle>("IntGrid", 9)
             .register_ldtk_entity::<PanelBundle>("Panel")
             .register_ldtk_entity::<LaserBundle>("Laser")
             .register_ldtk_entity::<FinishBundle>("Finish")
+            .register_ldtk_entity::<CrateBundle>("Crate")
+            .register_ldtk_entity::<TeleporterBundle>("Teleporter")
+            .register_ldtk_entity::<KeyBundle>("Key")
+            .register_ldtk_entity::<GateBundle>("Gate")
+            .register_ldtk_entity::<EmitterBundle>("Emitter")
+            .register_ldtk_entity::<MirrorBundle>("Mirror")
             .add_systems((spawn_level, hide_int_grid).in_schedule(OnEnter(GameState::Playing)))
+            .add_system(despawn_level.in_schedule(OnExit(GameState::Playing)))
             .add_systems(
                 (
+                    build_puzzle,
                     setup_panel,
                     respawn_on_level_reset,
                     spawn_lasers,
@@ -74,8 +142,31 @@ impl Plugin for LevelsPlugin {
                     reset_level,
                     hide_int_grid,
                     step_on_panel,
+                    sync_panels_with_puzzle,
                     finish_system,
                     level_transition.after(finish_system),
+                    hint_from_action,
+                    show_hint.after(hint_from_action),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_systems(
+                (
+                    spawn_crates,
+                    sync_crates_with_puzzle,
+                    spawn_teleporters,
+                    spawn_ice,
+                    spawn_conveyors,
+                    spawn_keys,
+                    spawn_locks,
+                    sync_keys_with_puzzle,
+                    notify_keys,
+                    spawn_gates,
+                    sync_gates_with_puzzle,
+                    spawn_emitters,
+                    spawn_mirrors,
+                    sync_mirrors_with_puzzle,
+                    sync_beams_with_puzzle,
                 )
                     .in_set(OnUpdate(GameState::Playing)),
             )
@@ -117,6 +208,59 @@ pub fn spawn_level(
     });
 }
 
+fn despawn_level(mut commands: Commands, ldtk_world_q: Query<Entity, With<Handle<LdtkAsset>>>) {
+    for entity in ldtk_world_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<CurrentPuzzle>();
+}
+
+fn build_puzzle(
+    mut commands: Commands,
+    level_q: Query<&Handle<LdtkLevel>, Added<Handle<LdtkLevel>>>,
+    ldtk_levels: Res<Assets<LdtkLevel>>,
+    mut hints: ResMut<HintCounter>,
+    mut checked_levels: Local<HashSet<String>>,
+) {
+    for level_handle in level_q.iter() {
+        let Some(ldtk_level) = ldtk_levels.get(level_handle) else { continue; };
+        match PuzzleState::from_ldtk_level(ldtk_level) {
+            Ok(puzzle) => {
+                // Solved once per level and off the main thread, resets don't need it again.
+                if cfg!(debug_assertions) && checked_levels.insert(ldtk_level.level.iid.clone()) {
+                    let puzzle = puzzle.clone();
+                    let identifier = ldtk_level.level.identifier.clone();
+                    AsyncComputeTaskPool::get()
+                        .spawn(async move {
+                            match solve(&puzzle) {
+                                Some(solution) => info!(
+                                    "{} is solvable in {} inputs",
+                                    identifier,
+                                    solution.input_count()
+                                ),
+                                None => warn!("{} is not solvable", identifier),
+                            }
+                        })
+                        .detach();
+                }
+                commands.insert_resource(CurrentPuzzle(puzzle));
+                commands.insert_resource(PuzzleHistory::default());
+                commands.insert_resource(MoveCounter::default());
+                if hints.level_iid.as_ref() != Some(&ldtk_level.level.iid) {
+                    *hints = HintCounter {
+                        level_iid: Some(ldtk_level.level.iid.clone()),
+                        used: 0,
+                    };
+                }
+            }
+            Err(err) => error!(
+                "Could not build puzzle for {}: {}",
+                ldtk_level.level.identifier, err
+            ),
+        }
+    }
+}
+
 fn hide_int_grid(mut ldtk_int_grid_q: Query<(&mut Visibility, &Name), Added<LayerMetadata>>) {
     for (mut visibility, name) in ldtk_int_grid_q.iter_mut() {
         if name.as_str() == "IntGrid" {
@@ -1,20 +1,16 @@
-use std::{cmp::min, time::Duration};
+use std::time::Duration;
 
 use bevy::prelude::*;
-use bevy_ecs_ldtk::{prelude::FieldValue, EntityInstance, GridCoords, LdtkEntity};
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity};
 use bevy_mod_aseprite::Aseprite;
 
 use crate::{
-    actions::MovementDirection,
     loading::SpriteAssets,
-    player::{
-        movement_effects::{MovementSideEffects, SideEffect},
-        Player,
-    },
+    puzzle::{ldtk::plate_from_entity_instance, PlateMode},
     ui::notifications::Notification,
 };
 
-use super::tiles::LaserType;
+use super::{tiles::LaserType, CurrentPuzzle, PuzzleStepped};
 
 #[derive(Clone, Bundle, LdtkEntity)]
 pub struct PanelBundle {
@@ -30,9 +26,6 @@ pub struct PanelBundle {
 #[derive(Component, Clone, Default, Debug)]
 pub struct PressurePlate {
     pub opens_laser: Option<LaserType>,
-    forbids_movement: Vec<MovementDirection>,
-    multi_movement: Vec<MovementDirection>,
-    multi_move_values: Vec<i32>,
     active: bool,
 }
 
@@ -42,111 +35,25 @@ impl PressurePlate {
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn setup_panel(
     mut commands: Commands,
-    mut panel_q: Query<(Entity, &mut PressurePlate, &EntityInstance, &Transform), Without<Sprite>>,
+    mut panel_q: Query<
+        (
+            Entity,
+            &mut PressurePlate,
+            &EntityInstance,
+            &GridCoords,
+            &Transform,
+        ),
+        Without<Sprite>,
+    >,
     sprites: Res<SpriteAssets>,
     aseprites: Res<Assets<Aseprite>>,
     texture_atlases: Res<Assets<TextureAtlas>>,
 ) {
-    for (entity, mut panel, entity_instance, transform) in panel_q.iter_mut() {
-        if let Some(door) = entity_instance
-            .field_instances
-            .iter()
-            .find(|field| field.identifier == "Door")
-            .and_then(|door| {
-                if let FieldValue::Enum(Some(door_type)) = door.value.clone() {
-                    Some(door_type)
-                } else {
-                    None
-                }
-            })
-            .and_then(|door| match door.as_str() {
-                "Red" => Some(LaserType::Red),
-                "Green" => Some(LaserType::Green),
-                "Blue" => Some(LaserType::Blue),
-                _ => None,
-            })
-        {
-            panel.opens_laser = Some(door);
-        }
-        if let Some(forbidden_movement) = entity_instance
-            .field_instances
-            .iter()
-            .find(|field| field.identifier == "Wasd_Disable")
-            .and_then(|movement| {
-                if let FieldValue::Enums(forbidden_movements) = movement.value.clone() {
-                    Some(forbidden_movements)
-                } else {
-                    None
-                }
-            })
-            .map(|forbidden_movements| {
-                forbidden_movements
-                    .iter()
-                    .flatten()
-                    .filter_map(|movement| match movement.as_str() {
-                        "W" => Some(MovementDirection::Up),
-                        "S" => Some(MovementDirection::Down),
-                        "A" => Some(MovementDirection::Left),
-                        "D" => Some(MovementDirection::Right),
-                        _ => None,
-                    })
-                    .collect::<Vec<MovementDirection>>()
-            })
-        {
-            panel.forbids_movement = forbidden_movement;
-        }
-
-        if let Some(multi_movement) = entity_instance
-            .field_instances
-            .iter()
-            .find(|field| field.identifier == "Wasd_Multi_Move")
-            .and_then(|multi_movement| {
-                if let FieldValue::Enums(multi_movements) = multi_movement.value.clone() {
-                    Some(multi_movements)
-                } else {
-                    None
-                }
-            })
-            .map(|forbidden_movements| {
-                forbidden_movements
-                    .iter()
-                    .flatten()
-                    .filter_map(|movement| match movement.as_str() {
-                        "W" => Some(MovementDirection::Up),
-                        "S" => Some(MovementDirection::Down),
-                        "A" => Some(MovementDirection::Left),
-                        "D" => Some(MovementDirection::Right),
-                        _ => None,
-                    })
-                    .collect::<Vec<MovementDirection>>()
-            })
-        {
-            panel.multi_movement = multi_movement;
-        }
-
-        if let Some(multi_move_values) = entity_instance
-            .field_instances
-            .iter()
-            .find(|field| field.identifier == "Multi_Move_Values")
-            .and_then(|multi_move_values| {
-                if let FieldValue::Ints(multi_move_values) = multi_move_values.value.clone() {
-                    Some(multi_move_values)
-                } else {
-                    None
-                }
-            })
-            .map(|multi_move_values| {
-                multi_move_values
-                    .iter()
-                    .flatten()
-                    .copied()
-                    .collect::<Vec<i32>>()
-            })
-        {
-            panel.multi_move_values = multi_move_values;
-        }
+    for (entity, mut panel, entity_instance, coords, transform) in panel_q.iter_mut() {
+        panel.opens_laser = plate_from_entity_instance(entity_instance, *coords).opens_laser;
 
         if let Some((atlas, sprite)) =
             sprite_for_panel(&panel, &sprites.plates, &aseprites, &texture_atlases)
@@ -170,30 +77,16 @@ fn sprite_for_panel(
     let Some(panel_aseprite) = aseprites.get(panel_aseprite) else { return None; };
     let Some(atlas) = texture_atlases.get(panel_aseprite.atlas()) else { return None; };
     let Some(door) = panel.opens_laser else { return None; };
-    let slice_name = match door {
-        LaserType::Red => {
-            if panel.is_active() {
-                "RedPressed"
-            } else {
-                "RedUnpressed"
-            }
-        }
-        LaserType::Green => {
-            if panel.is_active() {
-                "GreenPressed"
-            } else {
-                "GreenUnpressed"
-            }
+    let slice_name = format!(
+        "{}{}",
+        door,
+        if panel.is_active() {
+            "Pressed"
+        } else {
+            "Unpressed"
         }
-        LaserType::Blue => {
-            if panel.is_active() {
-                "BluePressed"
-            } else {
-                "BlueUnpressed"
-            }
-        }
-    };
-    let Some(slice) = panel_aseprite.info().slices.get(slice_name) else { return None; };
+    );
+    let Some(slice) = panel_aseprite.info().slices.get(&slice_name) else { return None; };
     (
         atlas.texture.clone_weak(),
         Sprite {
@@ -211,58 +104,91 @@ fn sprite_for_panel(
         .into()
 }
 
-#[allow(clippy::type_complexity)]
 pub fn step_on_panel(
-    mut player_q: Query<
-        (&GridCoords, &mut MovementSideEffects),
-        (With<Player>, Changed<GridCoords>),
-    >,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut notify: EventWriter<Notification>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    for PuzzleStepped(outcome) in puzzle_stepped.iter() {
+        for plate_index in outcome.released_plates.iter() {
+            if let Some(laser) = puzzle.layout().plates[*plate_index].opens_laser {
+                notify.send(Notification {
+                    text: format!("{} laser reactivated!", laser),
+                    duration: Duration::from_secs_f32(1.2),
+                });
+            }
+        }
+        for plate_index in outcome.pressed_plates.iter() {
+            let plate = &puzzle.layout().plates[*plate_index];
+            // A toggle plate that was just switched off is already among the released ones.
+            let switched_off = outcome.released_plates.contains(plate_index);
+            if let Some(laser) = plate.opens_laser.filter(|_| !switched_off) {
+                notify.send(Notification {
+                    text: match plate.mode {
+                        PlateMode::Timed(turns) => format!(
+                            "{} laser deactivated for {} {}!",
+                            laser,
+                            turns,
+                            if turns == 1 { "turn" } else { "turns" }
+                        ),
+                        PlateMode::Hold => format!("{} laser deactivated while held down!", laser),
+                        PlateMode::Latch | PlateMode::Toggle => {
+                            format!("{} laser deactivated!", laser)
+                        }
+                    },
+                    duration: Duration::from_secs_f32(1.2),
+                });
+            }
+            if !plate.wires.is_empty() && !switched_off {
+                notify.send(Notification {
+                    text: "Something clicked somewhere...".to_owned(),
+                    duration: Duration::from_secs_f32(1.2),
+                });
+            }
+            if plate.has_side_effects() {
+                notify.send(Notification {
+                    text: "You feel like something has changed...".to_owned(),
+                    duration: Duration::from_secs(2),
+                });
+            }
+        }
+    }
+}
+
+/// Presses and releases plates to match the puzzle, including after an undo.
+#[allow(clippy::type_complexity)]
+pub fn sync_panels_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
     mut panel_q: Query<(
         &GridCoords,
         &mut PressurePlate,
         Option<&mut Handle<Image>>,
         Option<&mut Sprite>,
     )>,
-    mut notify: EventWriter<Notification>,
     sprites: Res<SpriteAssets>,
     aseprites: Res<Assets<Aseprite>>,
     texture_atlases: Res<Assets<TextureAtlas>>,
 ) {
-    for (panel_coord, mut panel, mut image, mut sprite) in panel_q.iter_mut() {
-        for (player_coords, mut forbidden_movement) in player_q.iter_mut() {
-            if panel_coord == player_coords {
-                if let Some(laser) = panel.opens_laser {
-                    notify.send(Notification {
-                        text: format!("{} laser deactivated!", laser),
-                        duration: Duration::from_secs_f32(1.2),
-                    });
-                }
-                if !panel.forbids_movement.is_empty() || !panel.multi_movement.is_empty() {
-                    notify.send(Notification {
-                        text: "You feel like something has changed...".to_owned(),
-                        duration: Duration::from_secs(2),
-                    });
-                }
-                panel.active = true;
-                if let Some((atlas, new_sprite)) =
-                    sprite_for_panel(&panel, &sprites.plates, &aseprites, &texture_atlases)
-                {
-                    if let Some(entity_image) = image.as_mut() {
-                        **entity_image = atlas;
-                    }
-                    if let Some(sprite) = sprite.as_mut() {
-                        **sprite = new_sprite;
-                    }
-                }
-                for movement in panel.forbids_movement.iter() {
-                    forbidden_movement.set(*movement, SideEffect::DisabledMovement);
-                }
-                for i in 0..min(panel.multi_movement.len(), panel.multi_move_values.len()) {
-                    forbidden_movement.set(
-                        panel.multi_movement[i],
-                        SideEffect::MultiMove(panel.multi_move_values[i] as u32),
-                    );
-                }
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    for (coords, mut panel, mut image, mut sprite) in panel_q.iter_mut() {
+        let Some(plate_index) = puzzle.layout().plate_at(*coords) else { continue; };
+        let active = puzzle.is_plate_active(plate_index);
+        if panel.active == active {
+            continue;
+        }
+        panel.active = active;
+        if let Some((atlas, new_sprite)) =
+            sprite_for_panel(&panel, &sprites.plates, &aseprites, &texture_atlases)
+        {
+            if let Some(entity_image) = image.as_mut() {
+                **entity_image = atlas;
+            }
+            if let Some(sprite) = sprite.as_mut() {
+                **sprite = new_sprite;
             }
         }
     }
@@ -10,7 +10,7 @@ use crate::{
     ui::notifications::{CleanNotificationQueue, Notification},
 };
 
-use super::spawn_level;
+use super::{spawn_level, CurrentPuzzle};
 
 pub struct ResetLevelEvent;
 
@@ -31,6 +31,7 @@ pub fn reset_level(
         for entity in ldtk_wrold_q.iter() {
             commands.entity(entity).despawn_recursive();
         }
+        commands.remove_resource::<CurrentPuzzle>();
         spawn_level(
             commands,
             level_assets,
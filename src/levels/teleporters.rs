@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity};
+use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+
+use crate::{
+    loading::SpriteAssets,
+    player::color_control::ColorControl,
+    puzzle::ldtk::{color_from_enum, enum_field},
+};
+
+use super::RgbEntityAsepriteBundle;
+
+/// Visuals of a teleporter, where it leads to is part of the puzzle layout.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct TeleporterPad;
+
+#[derive(Bundle, LdtkEntity)]
+pub struct TeleporterBundle {
+    pad: TeleporterPad,
+
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
+
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
+pub fn spawn_teleporters(
+    mut commands: Commands,
+    pad_q: Query<(Entity, &EntityInstance), (With<TeleporterPad>, Without<AsepriteAnimation>)>,
+    sprites: Res<SpriteAssets>,
+    aseprites: Res<Assets<Aseprite>>,
+) {
+    let Some(aseprite) = aseprites.get(&sprites.teleporter) else { return; };
+    for (entity, entity_instance) in pad_q.iter() {
+        let color = enum_field(&entity_instance.field_instances, "Color")
+            .and_then(|color| color_from_enum(&color));
+        let tag_name = match color {
+            Some(ColorControl::Red) => "red_teleporter_idle",
+            Some(ColorControl::Green) => "green_teleporter_idle",
+            Some(ColorControl::Blue) => "blue_teleporter_idle",
+            None => "teleporter_idle",
+        };
+        let animation = AsepriteAnimation::new(aseprite.info(), tag_name);
+        commands.entity(entity).insert(RgbEntityAsepriteBundle {
+            texture_atlas: aseprite.atlas().clone_weak(),
+            sprite: TextureAtlasSprite::new(animation.current_frame()),
+            aseprite: sprites.teleporter.clone_weak(),
+            animation,
+        });
+    }
+}
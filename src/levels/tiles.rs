@@ -37,12 +37,42 @@ impl Laser {
     }
 }
 
+/// Color of a laser, each bit standing for one primary. Secondary colors only open
+/// once all of their primaries are on.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum LaserType {
     #[default]
-    Red,
-    Green,
-    Blue,
+    Red = 0b001,
+    Green = 0b010,
+    Blue = 0b100,
+    Yellow = 0b011,
+    Cyan = 0b110,
+    Magenta = 0b101,
+    White = 0b111,
+}
+
+impl LaserType {
+    pub fn from_int_grid_value(value: i32) -> Option<LaserType> {
+        match value {
+            3 => Some(LaserType::Red),
+            4 => Some(LaserType::Green),
+            5 => Some(LaserType::Blue),
+            6 => Some(LaserType::Yellow),
+            7 => Some(LaserType::Cyan),
+            8 => Some(LaserType::Magenta),
+            9 => Some(LaserType::White),
+            _ => None,
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether every primary of this color is part of `primaries`.
+    pub fn is_lit_by(self, primaries: u8) -> bool {
+        self.bits() & !primaries == 0
+    }
 }
 
 impl Display for LaserType {
@@ -51,6 +81,10 @@ impl Display for LaserType {
             LaserType::Red => write!(f, "Red"),
             LaserType::Green => write!(f, "Green"),
             LaserType::Blue => write!(f, "Blue"),
+            LaserType::Yellow => write!(f, "Yellow"),
+            LaserType::Cyan => write!(f, "Cyan"),
+            LaserType::Magenta => write!(f, "Magenta"),
+            LaserType::White => write!(f, "White"),
         }
     }
 }
@@ -69,14 +103,44 @@ pub struct TileBundle {
 
 impl LdtkIntCell for DoorBundle {
     fn bundle_int_cell(int_grid_cell: IntGridCell, _: &LayerInstance) -> Self {
-        let laser_type = match int_grid_cell.value {
-            3 => LaserType::Red,
-            4 => LaserType::Green,
-            5 => LaserType::Blue,
-            value => panic!("Invalid door value: {}", value),
-        };
+        let laser_type = LaserType::from_int_grid_value(int_grid_cell.value)
+            .unwrap_or_else(|| panic!("Invalid door value: {}", int_grid_cell.value));
         Self {
             door: Laser::new(laser_type),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_are_lit_by_all_of_their_primaries() {
+        let red = LaserType::Red.bits();
+        let green = LaserType::Green.bits();
+        let blue = LaserType::Blue.bits();
+        let cases = [
+            (LaserType::Red, red, true),
+            (LaserType::Red, green | blue, false),
+            (LaserType::Yellow, red | green, true),
+            (LaserType::Yellow, red, false),
+            (LaserType::Yellow, green | blue, false),
+            (LaserType::Cyan, green | blue, true),
+            (LaserType::Magenta, red | blue, true),
+            (LaserType::Magenta, blue, false),
+            (LaserType::White, red | green | blue, true),
+            (LaserType::White, red | green, false),
+            (LaserType::White, 0, false),
+        ];
+        for (laser_type, primaries, lit) in cases {
+            assert_eq!(
+                laser_type.is_lit_by(primaries),
+                lit,
+                "{} lit by {:03b}",
+                laser_type,
+                primaries
+            );
+        }
+    }
+}
@@ -3,7 +3,11 @@ mod audio;
 mod levels;
 mod loading;
 mod menu;
+mod pause;
 mod player;
+pub mod puzzle;
+mod replay;
+mod save;
 mod ui;
 
 use crate::actions::ActionsPlugin;
@@ -11,7 +15,10 @@ use crate::audio::InternalAudioPlugin;
 use crate::levels::LevelsPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
+use crate::pause::PausePlugin;
 use crate::player::PlayerPlugin;
+use crate::replay::ReplayPlugin;
+use crate::save::SavePlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
@@ -34,12 +41,16 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
+            // Before the loading plugin, which picks the state to continue to by the replay mode.
+            .add_plugin(ReplayPlugin)
             .add_plugin(LoadingPlugin)
             .add_plugin(MenuPlugin)
+            .add_plugin(PausePlugin)
             .add_plugin(LevelsPlugin { level_index: 0 })
             .add_plugin(ActionsPlugin)
             .add_plugin(InternalAudioPlugin)
             .add_plugin(PlayerPlugin)
+            .add_plugin(SavePlugin)
             .add_plugin(UIPlugin)
             .add_startup_system(spawn_camera);
 
@@ -1,3 +1,4 @@
+use crate::replay::ReplayMode;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
@@ -8,13 +9,16 @@ pub struct LoadingPlugin;
 
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading).continue_to_state(GameState::Playing),
-        )
-        .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, SpriteAssets>(GameState::Loading);
+        // Replays play back from their own start level, so the level select is left out.
+        let next_state = match app.world.get_resource::<ReplayMode>() {
+            Some(ReplayMode::Playback(_)) => GameState::Playing,
+            _ => GameState::Menu,
+        };
+        app.add_loading_state(LoadingState::new(GameState::Loading).continue_to_state(next_state))
+            .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, LevelAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, SpriteAssets>(GameState::Loading);
     }
 }
 
@@ -54,4 +58,18 @@ pub struct SpriteAssets {
     pub v_lasers: Handle<Aseprite>,
     #[asset(path = "sprites/lift.aseprite")]
     pub lift: Handle<Aseprite>,
+    #[asset(path = "sprites/crate.aseprite")]
+    pub crates: Handle<Aseprite>,
+    #[asset(path = "sprites/teleporter.aseprite")]
+    pub teleporter: Handle<Aseprite>,
+    #[asset(path = "sprites/ice.aseprite")]
+    pub ice: Handle<Aseprite>,
+    #[asset(path = "sprites/conveyor.aseprite")]
+    pub conveyor: Handle<Aseprite>,
+    #[asset(path = "sprites/keys.aseprite")]
+    pub keys: Handle<Aseprite>,
+    #[asset(path = "sprites/gates.aseprite")]
+    pub gates: Handle<Aseprite>,
+    #[asset(path = "sprites/beams.aseprite")]
+    pub beams: Handle<Aseprite>,
 }
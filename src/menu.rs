@@ -1,16 +1,30 @@
-use crate::loading::FontAssets;
+use std::collections::HashSet;
+
+use crate::loading::{FontAssets, LevelAssets};
+use crate::puzzle::ldtk::{level_chain_order, next_level_iids};
+use crate::save::{SaveData, SaveSlot, SAVE_SLOTS};
 use crate::GameState;
 use bevy::prelude::*;
+use bevy_ecs_ldtk::{LdtkAsset, LevelSelection};
 
 pub struct MenuPlugin;
 
-/// This plugin is responsible for the game menu (containing only one button...)
+/// This plugin is responsible for the level select screen
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
             .add_system(setup_menu.in_schedule(OnEnter(GameState::Menu)))
-            .add_system(click_play_button.in_set(OnUpdate(GameState::Menu)))
+            .add_systems(
+                (
+                    click_level_button,
+                    click_slot_button,
+                    cleanup_menu.run_if(resource_changed::<SaveData>()),
+                    setup_menu.run_if(resource_changed::<SaveData>()),
+                )
+                    .chain()
+                    .in_set(OnUpdate(GameState::Menu)),
+            )
             .add_system(cleanup_menu.in_schedule(OnExit(GameState::Menu)));
     }
 }
@@ -19,6 +33,8 @@ impl Plugin for MenuPlugin {
 struct ButtonColors {
     normal: Color,
     hovered: Color,
+    locked: Color,
+    selected: Color,
 }
 
 impl Default for ButtonColors {
@@ -26,49 +42,184 @@ impl Default for ButtonColors {
         ButtonColors {
             normal: Color::rgb(0.15, 0.15, 0.15),
             hovered: Color::rgb(0.25, 0.25, 0.25),
+            locked: Color::rgb(0.08, 0.08, 0.08),
+            selected: Color::rgb(0.3, 0.3, 0.45),
         }
     }
 }
 
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct SlotButton(u32);
+
+#[derive(Component)]
+struct LevelButton {
+    iid: String,
+    locked: bool,
+}
+
 fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
+    level_assets: Res<LevelAssets>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    save: Res<SaveData>,
+    slot: Res<SaveSlot>,
 ) {
+    let Some(ldtk) = ldtk_assets.get(&level_assets.level) else { return; };
+    let levels = &ldtk.project.levels;
+    let order = level_chain_order(levels);
+
+    // The first level is always open, every other one once a level leading to it is completed.
+    let mut unlocked: HashSet<&str> = order
+        .first()
+        .map(|first| levels[*first].iid.as_str())
+        .into_iter()
+        .collect();
+    unlocked.extend(save.current_level.iter().map(String::as_str));
+    let mut unlocked_next = Vec::new();
+    for level in levels.iter() {
+        if save.completed_levels.contains(&level.iid) {
+            unlocked.insert(level.iid.as_str());
+            unlocked_next.extend(next_level_iids(level));
+        }
+    }
+    unlocked.extend(unlocked_next.iter().map(String::as_str));
+
     commands
-        .spawn(ButtonBundle {
+        .spawn(NodeBundle {
             style: Style {
-                size: Size::new(Val::Px(120.0), Val::Px(50.0)),
-                margin: UiRect::all(Val::Auto),
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
                 ..Default::default()
             },
-            background_color: button_colors.normal.into(),
             ..Default::default()
         })
+        .insert(MenuRoot)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                "Play",
-                TextStyle {
-                    font: font_assets.fira_sans.clone(),
-                    font_size: 40.0,
-                    color: Color::rgb(0.9, 0.9, 0.9),
-                },
-            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::bottom(Val::Px(16.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for index in 0..SAVE_SLOTS {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(128.0), Val::Px(40.0)),
+                                    margin: UiRect::all(Val::Px(4.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                background_color: slot_color(&button_colors, &slot, index).into(),
+                                ..Default::default()
+                            })
+                            .insert(SlotButton(index))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("Slot {}", index + 1),
+                                    TextStyle {
+                                        font: font_assets.fira_sans.clone(),
+                                        font_size: 24.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                ));
+                            });
+                    }
+                });
+            parent.spawn(
+                TextBundle::from_section(
+                    "Select a level",
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 48.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(24.0)),
+                    ..Default::default()
+                }),
+            );
+            for (position, index) in order.iter().enumerate() {
+                let level = &levels[*index];
+                let locked = !unlocked.contains(level.iid.as_str());
+                let completed = save.completed_levels.contains(&level.iid);
+                let label = if locked {
+                    format!("Level {} - locked", position + 1)
+                } else if let Some(best) = save.best_moves.get(&level.iid) {
+                    format!("Level {} - best: {} moves", position + 1, best)
+                } else if completed {
+                    format!("Level {} - completed", position + 1)
+                } else {
+                    format!("Level {}", position + 1)
+                };
+                let text_color = if locked {
+                    Color::rgb(0.4, 0.4, 0.4)
+                } else if completed {
+                    Color::rgb(0.6, 0.9, 0.6)
+                } else {
+                    Color::rgb(0.9, 0.9, 0.9)
+                };
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(400.0), Val::Px(50.0)),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        background_color: if locked {
+                            button_colors.locked.into()
+                        } else {
+                            button_colors.normal.into()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(LevelButton {
+                        iid: level.iid.clone(),
+                        locked,
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 32.0,
+                                color: text_color,
+                            },
+                        ));
+                    });
+            }
         });
 }
 
-type InteractionWithColor<'a> = (&'a Interaction, &'a mut BackgroundColor);
+type LevelButtonInteraction<'a> = (&'a Interaction, &'a mut BackgroundColor, &'a LevelButton);
 type ClickButtonFilter = (Changed<Interaction>, With<Button>);
-fn click_play_button(
+fn click_level_button(
     button_colors: Res<ButtonColors>,
     mut state: ResMut<NextState<GameState>>,
-    mut interaction_query: Query<InteractionWithColor, ClickButtonFilter>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut interaction_query: Query<LevelButtonInteraction, ClickButtonFilter>,
 ) {
-    for (interaction, mut color) in &mut interaction_query {
+    for (interaction, mut color, level_button) in &mut interaction_query {
+        if level_button.locked {
+            continue;
+        }
         match *interaction {
             Interaction::Clicked => {
+                *level_selection = LevelSelection::Iid(level_button.iid.clone());
                 state.set(GameState::Playing);
             }
             Interaction::Hovered => {
@@ -81,6 +232,42 @@ fn click_play_button(
     }
 }
 
-fn cleanup_menu(mut commands: Commands, button: Query<Entity, With<Button>>) {
-    commands.entity(button.single()).despawn_recursive();
+fn slot_color(button_colors: &ButtonColors, slot: &SaveSlot, index: u32) -> Color {
+    if slot.0 == index {
+        button_colors.selected
+    } else {
+        button_colors.normal
+    }
+}
+
+/// Picking another slot reloads its progress, which rebuilds the menu.
+fn click_slot_button(
+    button_colors: Res<ButtonColors>,
+    mut slot: ResMut<SaveSlot>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &SlotButton),
+        ClickButtonFilter,
+    >,
+) {
+    for (interaction, mut color, slot_button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                if slot.0 != slot_button.0 {
+                    slot.0 = slot_button.0;
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = slot_color(&button_colors, &slot, slot_button.0).into();
+            }
+        }
+    }
+}
+
+fn cleanup_menu(mut commands: Commands, menu_root: Query<Entity, With<MenuRoot>>) {
+    for entity in menu_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
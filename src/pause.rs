@@ -0,0 +1,411 @@
+use bevy::{app::AppExit, prelude::*};
+use bevy_mod_aseprite::AsepriteAnimation;
+
+use crate::{
+    actions::{
+        bindings::{key_label, Bindings},
+        game_control::GameControl,
+        gamepad::GamepadInput,
+        input_buffer::{InputBuffer, MAX_INPUT_BUFFER_DEPTH},
+    },
+    audio::AudioConfig,
+    levels::reset::ResetLevelEvent,
+    loading::FontAssets,
+    ui::buttons_styles::{BUTTON_CLICK_BG_COLOR, BUTTON_DEFAULT_BG_COLOR, BUTTON_HOVER_BG_COLOR},
+    GameState,
+};
+
+/// Sub-state of [`GameState::Playing`]. Everything in `OnUpdate(GameState::Playing)` only runs
+/// while [`PauseState::Running`].
+#[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+    Settings,
+    Controls,
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PauseState>()
+            .configure_set(OnUpdate(GameState::Playing).run_if(in_state(PauseState::Running)))
+            .init_resource::<PendingRebind>()
+            .add_system(toggle_pause.run_if(in_state(GameState::Playing)))
+            .add_system(freeze_animations.in_schedule(OnExit(PauseState::Running)))
+            .add_system(unfreeze_animations.in_schedule(OnEnter(PauseState::Running)))
+            .add_system(spawn_pause_menu.in_schedule(OnEnter(PauseState::Paused)))
+            .add_system(despawn_pause_menu.in_schedule(OnExit(PauseState::Paused)))
+            .add_system(spawn_settings_menu.in_schedule(OnEnter(PauseState::Settings)))
+            .add_system(despawn_pause_menu.in_schedule(OnExit(PauseState::Settings)))
+            .add_system(spawn_controls_menu.in_schedule(OnEnter(PauseState::Controls)))
+            .add_systems(
+                (despawn_pause_menu, cancel_rebind).in_schedule(OnExit(PauseState::Controls)),
+            )
+            .add_systems(
+                (capture_rebind.after(toggle_pause), relabel_controls)
+                    .chain()
+                    .distributive_run_if(in_state(PauseState::Controls)),
+            )
+            .add_system(click_pause_button.run_if(is_paused))
+            .add_system(resume_on_exit.in_schedule(OnExit(GameState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+/// Set on animations that were playing when the game got paused.
+#[derive(Component)]
+struct FrozenAnimation;
+
+/// Control waiting for its new key on the controls page.
+#[derive(Resource, Default)]
+struct PendingRebind(Option<GameControl>);
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    RestartLevel,
+    LevelSelect,
+    Settings,
+    Quit,
+    ToggleSound,
+    InputBufferDepth,
+    Controls,
+    Rebind(GameControl),
+    ResetBindings,
+    Back,
+}
+
+fn is_paused(pause_state: Res<State<PauseState>>) -> bool {
+    pause_state.0 != PauseState::Running
+}
+
+/// Page that `Back` and the pause key lead to.
+fn previous_page(pause_state: &PauseState) -> PauseState {
+    match pause_state {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+        PauseState::Settings => PauseState::Paused,
+        PauseState::Controls => PauseState::Settings,
+    }
+}
+
+fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    gamepad_input: GamepadInput,
+    pending_rebind: Res<PendingRebind>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    // The key is going to be bound instead.
+    if pending_rebind.0.is_some() {
+        return;
+    }
+    if GameControl::Pause.check_input(
+        &bindings,
+        &|input, code| input.just_pressed(code),
+        &keyboard_input,
+    ) || gamepad_input.just_pressed(GameControl::Pause)
+    {
+        next_pause_state.set(previous_page(&pause_state.0));
+    }
+}
+
+fn freeze_animations(
+    mut commands: Commands,
+    mut animation_q: Query<(Entity, &mut AsepriteAnimation)>,
+) {
+    for (entity, mut animation) in animation_q.iter_mut() {
+        if animation.is_playing() {
+            animation.pause();
+            commands.entity(entity).insert(FrozenAnimation);
+        }
+    }
+}
+
+fn unfreeze_animations(
+    mut commands: Commands,
+    mut animation_q: Query<(Entity, &mut AsepriteAnimation), With<FrozenAnimation>>,
+) {
+    for (entity, mut animation) in animation_q.iter_mut() {
+        animation.play();
+        commands.entity(entity).remove::<FrozenAnimation>();
+    }
+}
+
+fn resume_on_exit(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+fn spawn_pause_menu(mut commands: Commands, font_assets: Res<FontAssets>) {
+    spawn_menu(
+        &mut commands,
+        &font_assets,
+        "Paused",
+        &[
+            (PauseButton::Resume, "Resume".to_owned()),
+            (PauseButton::RestartLevel, "Restart level".to_owned()),
+            (PauseButton::LevelSelect, "Level select".to_owned()),
+            (PauseButton::Settings, "Settings".to_owned()),
+            (PauseButton::Quit, "Quit".to_owned()),
+        ],
+    );
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    audio_config: Res<AudioConfig>,
+    input_buffer: Res<InputBuffer>,
+) {
+    spawn_menu(
+        &mut commands,
+        &font_assets,
+        "Settings",
+        &[
+            (PauseButton::ToggleSound, sound_label(&audio_config)),
+            (
+                PauseButton::InputBufferDepth,
+                input_buffer_label(&input_buffer),
+            ),
+            (PauseButton::Controls, "Controls".to_owned()),
+            (PauseButton::Back, "Back".to_owned()),
+        ],
+    );
+}
+
+fn input_buffer_label(input_buffer: &InputBuffer) -> String {
+    format!("Input buffer: {}", input_buffer.depth)
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    bindings: Res<Bindings>,
+) {
+    let mut buttons: Vec<_> = GameControl::all()
+        .into_iter()
+        .map(|control| {
+            (
+                PauseButton::Rebind(control),
+                control_label(control, &bindings, None),
+            )
+        })
+        .collect();
+    buttons.push((PauseButton::ResetBindings, "Reset to defaults".to_owned()));
+    buttons.push((PauseButton::Back, "Back".to_owned()));
+    spawn_menu(&mut commands, &font_assets, "Controls", &buttons);
+}
+
+fn control_label(
+    control: GameControl,
+    bindings: &Bindings,
+    pending: Option<GameControl>,
+) -> String {
+    if pending == Some(control) {
+        return format!("{}: press a key to add or remove", control.name());
+    }
+    let keys = bindings
+        .keys(control)
+        .iter()
+        .map(|key| key_label(*key))
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        format!("{}: unbound", control.name())
+    } else {
+        format!("{}: {}", control.name(), keys.join(", "))
+    }
+}
+
+/// Adds the next pressed key to the control picked on the controls page, or removes it if it
+/// was already bound there. `Escape` cancels.
+fn capture_rebind(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut pending_rebind: ResMut<PendingRebind>,
+    mut bindings: ResMut<Bindings>,
+) {
+    let Some(control) = pending_rebind.0 else { return; };
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else { return; };
+    pending_rebind.0 = None;
+    if key == KeyCode::Escape {
+        return;
+    }
+    // Taking or removing the only pause key would leave no way back into this menu.
+    if bindings.keys(GameControl::Pause) == [key] {
+        warn!("{:?} is the only key bound to pause", key);
+        return;
+    }
+    if bindings.keys(control).contains(&key) {
+        bindings.remove(control, key);
+    } else {
+        bindings.add(control, key);
+    }
+}
+
+fn cancel_rebind(mut pending_rebind: ResMut<PendingRebind>) {
+    pending_rebind.0 = None;
+}
+
+fn relabel_controls(
+    bindings: Res<Bindings>,
+    pending_rebind: Res<PendingRebind>,
+    button_q: Query<(&PauseButton, &Children)>,
+    mut text_q: Query<&mut Text>,
+) {
+    if !bindings.is_changed() && !pending_rebind.is_changed() {
+        return;
+    }
+    for (button, children) in button_q.iter() {
+        let PauseButton::Rebind(control) = button else { continue; };
+        for child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(*child) {
+                text.sections[0].value = control_label(*control, &bindings, pending_rebind.0);
+            }
+        }
+    }
+}
+
+fn sound_label(audio_config: &AudioConfig) -> String {
+    if audio_config.mute {
+        "Sound: off".to_owned()
+    } else {
+        "Sound: on".to_owned()
+    }
+}
+
+fn spawn_menu(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    title: &str,
+    buttons: &[(PauseButton, String)],
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(PauseMenu)
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 48.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(24.)),
+                    ..default()
+                }),
+            );
+            for (button, label) in buttons.iter() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(320.), Val::Px(44.)),
+                            margin: UiRect::all(Val::Px(3.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON_DEFAULT_BG_COLOR.into(),
+                        ..default()
+                    })
+                    .insert(*button)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label.clone(),
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 24.,
+                                color: Color::rgb_u8(21, 18, 23),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn despawn_pause_menu(mut commands: Commands, menu_q: Query<Entity, With<PauseMenu>>) {
+    for entity in menu_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn click_pause_button(
+    mut button_q: Query<
+        (&Interaction, &mut BackgroundColor, &PauseButton, &Children),
+        Changed<Interaction>,
+    >,
+    mut text_q: Query<&mut Text>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut reset_level_event: EventWriter<ResetLevelEvent>,
+    mut audio_config: ResMut<AudioConfig>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut bindings: ResMut<Bindings>,
+    mut pending_rebind: ResMut<PendingRebind>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, mut background_color, button, children) in button_q.iter_mut() {
+        match interaction {
+            Interaction::Hovered => *background_color = BUTTON_HOVER_BG_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_DEFAULT_BG_COLOR.into(),
+            Interaction::Clicked => {
+                *background_color = BUTTON_CLICK_BG_COLOR.into();
+                match button {
+                    PauseButton::Resume => next_pause_state.set(PauseState::Running),
+                    PauseButton::RestartLevel => {
+                        reset_level_event.send(ResetLevelEvent);
+                        next_pause_state.set(PauseState::Running);
+                    }
+                    PauseButton::LevelSelect => next_game_state.set(GameState::Menu),
+                    PauseButton::Settings => next_pause_state.set(PauseState::Settings),
+                    PauseButton::Quit => exit.send(AppExit),
+                    PauseButton::ToggleSound => {
+                        audio_config.mute = !audio_config.mute;
+                        for child in children.iter() {
+                            if let Ok(mut text) = text_q.get_mut(*child) {
+                                text.sections[0].value = sound_label(&audio_config);
+                            }
+                        }
+                    }
+                    PauseButton::InputBufferDepth => {
+                        input_buffer.depth =
+                            (input_buffer.depth + 1) % (MAX_INPUT_BUFFER_DEPTH + 1);
+                        for child in children.iter() {
+                            if let Ok(mut text) = text_q.get_mut(*child) {
+                                text.sections[0].value = input_buffer_label(&input_buffer);
+                            }
+                        }
+                    }
+                    PauseButton::Controls => next_pause_state.set(PauseState::Controls),
+                    PauseButton::Rebind(control) => pending_rebind.0 = Some(*control),
+                    PauseButton::ResetBindings => {
+                        pending_rebind.0 = None;
+                        *bindings = Bindings::default();
+                    }
+                    PauseButton::Back => next_pause_state.set(previous_page(&pause_state.0)),
+                }
+            }
+        }
+    }
+}
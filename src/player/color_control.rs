@@ -1,29 +1,37 @@
 use bevy::prelude::*;
 
-use crate::{actions::Actions, levels::tiles::LaserType};
+use crate::{
+    actions::Actions,
+    levels::{
+        tiles::LaserType, CurrentPuzzle, MoveCounter, PuzzleHistory, PuzzleStepped, TurnSnapshot,
+    },
+    puzzle::PuzzleAction,
+};
 
-#[derive(Component, Default, Clone, Copy, Debug, PartialEq, Eq)]
+use super::Player;
+
+#[derive(Component, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ColorControl {
     Red,
+    Green,
     #[default]
     Blue,
 }
 
 impl ColorControl {
-    pub fn switch(&mut self) {
-        match *self {
-            ColorControl::Red => {
-                *self = ColorControl::Blue;
-            }
-            ColorControl::Blue => {
-                *self = ColorControl::Red;
-            }
-        }
+    /// Color after this one in `cycle`, wrapping around. Colors missing from `cycle` go to its start.
+    pub fn next_in(&self, cycle: &[ColorControl]) -> ColorControl {
+        let next = match cycle.iter().position(|color| color == self) {
+            Some(index) => cycle.get(index + 1).or(cycle.first()),
+            None => cycle.first(),
+        };
+        next.copied().unwrap_or(*self)
     }
 
     pub fn as_laser_type(&self) -> LaserType {
         match *self {
             ColorControl::Red => LaserType::Red,
+            ColorControl::Green => LaserType::Green,
             ColorControl::Blue => LaserType::Blue,
         }
     }
@@ -32,12 +40,48 @@ impl ColorControl {
 pub fn set_color_control_from_action(
     mut actions: EventReader<Actions>,
     mut color_control_q: Query<&mut ColorControl>,
+    mut puzzle: Option<ResMut<CurrentPuzzle>>,
+    mut history: ResMut<PuzzleHistory>,
+    mut moves: ResMut<MoveCounter>,
+    mut puzzle_stepped: EventWriter<PuzzleStepped>,
 ) {
+    let Some(puzzle) = puzzle.as_mut() else { return; };
     for action in actions.iter() {
         if action.color_switch.is_some() {
-            for mut color_control in color_control_q.iter_mut() {
-                color_control.switch();
+            let snapshot = TurnSnapshot {
+                puzzle: puzzle.0.clone(),
+                moves: *moves,
+            };
+            let outcome = puzzle.step(PuzzleAction::SwitchColor);
+            if !outcome.is_noop() {
+                // Switching mid multi-move is part of the turn that started the move.
+                if !snapshot.puzzle.is_moving() {
+                    history.record(snapshot);
+                }
+                **moves += 1;
+            }
+            if outcome.color_switched {
+                for mut color_control in color_control_q.iter_mut() {
+                    *color_control = puzzle.color;
+                }
             }
+            puzzle_stepped.send(PuzzleStepped(outcome));
+        }
+    }
+}
+
+/// Puts the player in the puzzle's color when a level starts or a turn is undone.
+pub fn sync_color_control_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut color_control_q: Query<&mut ColorControl, With<Player>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    for mut color_control in color_control_q.iter_mut() {
+        if *color_control != puzzle.color {
+            *color_control = puzzle.color;
         }
     }
 }
@@ -1,31 +1,27 @@
 use bevy::prelude::*;
-use bevy_ecs_ldtk::GridCoords;
 use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
 
-use crate::{animation_finished, levels::tiles::Laser, loading::SpriteAssets};
+use crate::{animation_finished, levels::PuzzleStepped, loading::SpriteAssets};
 
-use super::{color_control::ColorControl, Player};
+use super::Player;
 
 pub struct Death;
 
 #[derive(Component)]
 pub struct Dying;
 
-#[allow(clippy::type_complexity)]
+/// Kills the player once a step leaves them on a closed laser or beam, whether they walked
+/// onto it, switched color, turned a mirror, or a timed laser or timed plate closed it under
+/// them at the end of the turn.
 pub fn die_on_tile_with_door(
     mut commands: Commands,
-    player_q: Query<
-        (Entity, &GridCoords),
-        (
-            Or<(Changed<ColorControl>, Changed<GridCoords>)>,
-            With<Player>,
-        ),
-    >,
-    door_q: Query<(&GridCoords, &Laser)>,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    player_q: Query<Entity, With<Player>>,
 ) {
-    for (entity, player_coords) in player_q.iter() {
-        for (door_coords, door) in door_q.iter() {
-            if player_coords == door_coords && !door.is_open {
+    for PuzzleStepped(outcome) in puzzle_stepped.iter() {
+        // `teleport_player` takes over once the player has warped onto the laser.
+        if outcome.died && outcome.teleported_to.is_none() {
+            for entity in player_q.iter() {
                 commands.entity(entity).insert(Dying);
             }
         }
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+use crate::levels::CurrentPuzzle;
+
+use super::{color_control::ColorControl, Player};
+
+/// Colors of the keys the player holds, in the order they were picked up.
+#[derive(Component, Default, Clone, Debug, PartialEq, Eq)]
+pub struct Inventory(pub Vec<ColorControl>);
+
+/// Takes the held keys from the puzzle after a step, an undo or a level reset.
+pub fn sync_inventory_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut inventory_q: Query<&mut Inventory, With<Player>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    for mut inventory in inventory_q.iter_mut() {
+        if inventory.0 != puzzle.inventory {
+            inventory.0 = puzzle.inventory.clone();
+        }
+    }
+}
@@ -1,8 +1,11 @@
 pub mod color_control;
 pub mod death;
+pub mod inventory;
 pub mod movement;
 pub mod movement_effects;
 pub mod open_lasers;
+pub mod teleport;
+pub mod undo;
 
 use crate::levels::RgbEntityAsepriteBundle;
 use crate::loading::SpriteAssets;
@@ -11,14 +14,19 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
 
-use self::color_control::{set_color_control_from_action, ColorControl};
+use self::color_control::{
+    set_color_control_from_action, sync_color_control_with_puzzle, ColorControl,
+};
 use self::death::{die_on_tile_with_door, play_death_animation, Death, Dying};
+use self::inventory::{sync_inventory_with_puzzle, Inventory};
 use self::movement::{
     change_transform_based_on_grid, next_movement_state, player_action_to_movement,
     tween_translations, MovementState,
 };
-use self::movement_effects::MovementSideEffects;
+use self::movement_effects::{sync_side_effects_with_puzzle, MovementSideEffects};
 use self::open_lasers::*;
+use self::teleport::teleport_player;
+use self::undo::undo_redo_from_action;
 
 pub struct PlayerPlugin;
 
@@ -37,6 +45,7 @@ pub struct PlayerBundle {
     movement_state: MovementState,
     color_control: ColorControl,
     forbidden_movement: MovementSideEffects,
+    inventory: Inventory,
 }
 
 impl Plugin for PlayerPlugin {
@@ -49,17 +58,38 @@ impl Plugin for PlayerPlugin {
                     player_action_to_movement,
                     change_transform_based_on_grid.after(spawn_player_sprite),
                     tween_translations.after(change_transform_based_on_grid),
+                    undo_redo_from_action
+                        .after(player_action_to_movement)
+                        .after(set_color_control_from_action),
                     die_on_tile_with_door
-                        .after(open_lasers)
-                        .after(next_movement_state),
+                        .after(player_action_to_movement)
+                        .after(next_movement_state)
+                        .after(set_color_control_from_action),
                     next_movement_state,
                     switch_player_animation_color_change,
                     switch_player_animation_movement_change,
-                    open_lasers.after(set_color_control_from_action),
+                    open_lasers
+                        .after(set_color_control_from_action)
+                        .after(undo_redo_from_action),
                     set_color_control_from_action,
                     play_death_animation,
+                    teleport_player.before(change_transform_based_on_grid),
+                    sync_color_control_with_puzzle
+                        .after(set_color_control_from_action)
+                        .after(undo_redo_from_action),
+                    sync_side_effects_with_puzzle
+                        .after(player_action_to_movement)
+                        .after(next_movement_state)
+                        .after(undo_redo_from_action),
                 )
                     .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(
+                sync_inventory_with_puzzle
+                    .after(player_action_to_movement)
+                    .after(next_movement_state)
+                    .after(undo_redo_from_action)
+                    .in_set(OnUpdate(GameState::Playing)),
             );
     }
 }
@@ -2,16 +2,15 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy_ecs_ldtk::*;
-use bevy_ecs_tilemap::{prelude::TilemapTileSize, tiles::TileStorage};
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
 
 use crate::{
     actions::{Actions, MovementDirection},
-    levels::tiles::Wall,
+    levels::{CurrentPuzzle, MoveCounter, PuzzleHistory, PuzzleStepped, TurnSnapshot},
+    puzzle::{PuzzleAction, PuzzleState, StepOutcome},
 };
 
-use super::{
-    color_control::ColorControl, death::Dying, movement_effects::MovementSideEffects, Player,
-};
+use super::{color_control::ColorControl, death::Dying, Player};
 
 #[derive(Component, Default, Clone, Copy)]
 pub enum MovementState {
@@ -22,6 +21,8 @@ pub enum MovementState {
         direction: MovementDirection,
         left: u32,
     },
+    /// Walking onto a teleporter, then warping to where the puzzle put the player.
+    Teleporting(MovementDirection),
 }
 
 pub struct AnimationInfo {
@@ -34,36 +35,40 @@ impl MovementState {
         let tag_name = match self {
             MovementState::Idle => match color_control {
                 ColorControl::Red => "red_idle",
+                ColorControl::Green => "green_idle",
                 ColorControl::Blue => "blue_idle",
             },
-            MovementState::Moving(direction) | MovementState::MultiMoving { direction, .. } => {
-                match direction {
-                    MovementDirection::Up => "walk_up",
-                    MovementDirection::Down => match color_control {
-                        ColorControl::Red => "red_walk_down",
-                        ColorControl::Blue => "blue_walk_down",
-                    },
-                    MovementDirection::Left => match color_control {
-                        ColorControl::Red => "red_walk_right",
-                        ColorControl::Blue => "blue_walk_right",
-                    },
-                    MovementDirection::Right => match color_control {
-                        ColorControl::Red => "red_walk_right",
-                        ColorControl::Blue => "blue_walk_right",
-                    },
-                }
-            }
+            MovementState::Moving(direction)
+            | MovementState::MultiMoving { direction, .. }
+            | MovementState::Teleporting(direction) => match direction {
+                MovementDirection::Up => "walk_up",
+                MovementDirection::Down => match color_control {
+                    ColorControl::Red => "red_walk_down",
+                    ColorControl::Green => "green_walk_down",
+                    ColorControl::Blue => "blue_walk_down",
+                },
+                MovementDirection::Left => match color_control {
+                    ColorControl::Red => "red_walk_right",
+                    ColorControl::Green => "green_walk_right",
+                    ColorControl::Blue => "blue_walk_right",
+                },
+                MovementDirection::Right => match color_control {
+                    ColorControl::Red => "red_walk_right",
+                    ColorControl::Green => "green_walk_right",
+                    ColorControl::Blue => "blue_walk_right",
+                },
+            },
         };
         let flip_x = match self {
             MovementState::Idle => false,
-            MovementState::Moving(direction) | MovementState::MultiMoving { direction, .. } => {
-                match direction {
-                    MovementDirection::Up => false,
-                    MovementDirection::Down => false,
-                    MovementDirection::Left => true,
-                    MovementDirection::Right => false,
-                }
-            }
+            MovementState::Moving(direction)
+            | MovementState::MultiMoving { direction, .. }
+            | MovementState::Teleporting(direction) => match direction {
+                MovementDirection::Up => false,
+                MovementDirection::Down => false,
+                MovementDirection::Left => true,
+                MovementDirection::Right => false,
+            },
         };
         AnimationInfo { tag_name, flip_x }
     }
@@ -73,41 +78,25 @@ impl MovementState {
             MovementState::Idle => false,
             MovementState::Moving(_) => true,
             MovementState::MultiMoving { .. } => true,
+            MovementState::Teleporting(_) => true,
         }
     }
 
-    pub fn apply_movement(
-        &mut self,
-        coords: &mut GridCoords,
-        tile_storage_q: &Query<(&TileStorage, &Name)>,
-        tiles_q: &Query<Option<&Wall>>,
-    ) {
-        match self {
-            MovementState::Idle => {}
-            MovementState::Moving(direction) | MovementState::MultiMoving { direction, .. } => {
-                let direction = direction.as_ivec2();
-                let next_grid_coords = GridCoords {
-                    x: coords.x + direction.x,
-                    y: coords.y + direction.y,
-                };
-                let is_wall = tile_storage_q
-                    .iter()
-                    .find(|(_, name)| name.as_str() == "IntGrid")
-                    .and_then(|(tile_storage, _)| tile_storage.get(&next_grid_coords.into()))
-                    .map(|tile| {
-                        tiles_q
-                            .get(tile)
-                            .map(|tile| tile.is_some())
-                            .unwrap_or(false)
-                    })
-                    .unwrap_or(false);
-
-                if is_wall {
-                    *self = MovementState::Idle;
-                } else {
-                    *coords = next_grid_coords;
-                }
-            }
+    /// Movement the presentation should play after `puzzle` moved the player along `direction`.
+    pub fn from_puzzle(
+        puzzle: &PuzzleState,
+        direction: MovementDirection,
+        outcome: &StepOutcome,
+    ) -> Self {
+        if outcome.teleported_to.is_some() {
+            return MovementState::Teleporting(direction);
+        }
+        match puzzle.momentum {
+            Some(momentum) => MovementState::MultiMoving {
+                direction: momentum.direction,
+                left: momentum.left,
+            },
+            None => MovementState::Moving(direction),
         }
     }
 }
@@ -124,24 +113,35 @@ pub struct TweenTranslation {
 pub fn player_action_to_movement(
     mut actions: EventReader<Actions>,
     mut player_query: Query<
-        (&MovementSideEffects, &mut MovementState, &mut GridCoords),
-        (With<Player>, Without<Dying>),
+        (&mut MovementState, &mut GridCoords),
+        (With<Player>, Without<Dying>, Without<TweenTranslation>),
     >,
-    tile_storage_q: Query<(&TileStorage, &Name)>,
-    tiles_q: Query<Option<&Wall>>,
+    mut puzzle: Option<ResMut<CurrentPuzzle>>,
+    mut history: ResMut<PuzzleHistory>,
+    mut moves: ResMut<MoveCounter>,
+    mut puzzle_stepped: EventWriter<PuzzleStepped>,
 ) {
+    let Some(puzzle) = puzzle.as_mut() else { return; };
     for actions in actions.iter() {
-        if let Some(player_movement) = &actions.player_movement {
-            for (side_effects, mut movement_state, mut coords) in player_query.iter_mut() {
+        if let Some(player_movement) = actions.player_movement {
+            for (mut movement_state, mut coords) in player_query.iter_mut() {
                 if movement_state.is_moving() {
                     continue;
                 }
-                let default_movement = MovementState::Moving(*player_movement);
-                *movement_state = side_effects
-                    .get(*player_movement)
-                    .transform_movement_state(default_movement);
-
-                movement_state.apply_movement(&mut coords, &tile_storage_q, &tiles_q);
+                let snapshot = TurnSnapshot {
+                    puzzle: puzzle.0.clone(),
+                    moves: *moves,
+                };
+                let outcome = puzzle.step(PuzzleAction::Move(player_movement));
+                if !outcome.is_noop() {
+                    history.record(snapshot);
+                    **moves += 1;
+                }
+                if let Some(moved_to) = outcome.moved_to {
+                    *coords = moved_to;
+                    *movement_state = MovementState::from_puzzle(puzzle, player_movement, &outcome);
+                }
+                puzzle_stepped.send(PuzzleStepped(outcome));
             }
         }
     }
@@ -151,13 +151,16 @@ pub fn player_action_to_movement(
 pub fn change_transform_based_on_grid(
     mut commands: Commands,
     player_query: Query<
-        (Entity, &Transform, &GridCoords),
+        (Entity, &Transform, &GridCoords, &MovementState),
         (Without<TweenTranslation>, Changed<GridCoords>, With<Player>),
     >,
     tilemap_size_q: Query<&TilemapTileSize>,
 ) {
     let Some(tile_size) = tilemap_size_q.iter().next() else { return; };
-    for (entity, transform, grid_coords) in player_query.iter() {
+    for (entity, transform, grid_coords, movement_state) in player_query.iter() {
+        if matches!(movement_state, MovementState::Teleporting(_)) {
+            continue;
+        }
         let target_pos = Vec3::new(
             grid_coords.x as f32 * tile_size.x + tile_size.x / 2.0,
             grid_coords.y as f32 * tile_size.y + tile_size.y / 2.0 + 8.,
@@ -192,25 +195,28 @@ pub fn tween_translations(
 pub fn next_movement_state(
     mut removed: RemovedComponents<TweenTranslation>,
     mut player_query: Query<(&mut MovementState, &mut GridCoords), Without<Dying>>,
-    tile_storage_q: Query<(&TileStorage, &Name)>,
-    tiles_q: Query<Option<&Wall>>,
+    mut puzzle: Option<ResMut<CurrentPuzzle>>,
+    mut puzzle_stepped: EventWriter<PuzzleStepped>,
 ) {
     for entity in removed.iter() {
         if let Ok((mut movement_state, mut grid_coords)) = player_query.get_mut(entity) {
             *movement_state = match *movement_state {
                 MovementState::Idle => MovementState::Idle,
                 MovementState::Moving(_) => MovementState::Idle,
-                MovementState::MultiMoving { direction, left } => {
-                    if left > 1 {
-                        movement_state.apply_movement(&mut grid_coords, &tile_storage_q, &tiles_q);
-                    }
-                    match left {
-                        2.. => MovementState::MultiMoving {
-                            direction,
-                            left: left - 1,
-                        },
-                        _ => MovementState::Idle,
-                    }
+                // The warp itself is played by `teleport_player`.
+                MovementState::Teleporting(direction) => MovementState::Teleporting(direction),
+                MovementState::MultiMoving { direction, .. } => {
+                    let Some(puzzle) = puzzle.as_mut() else { continue; };
+                    let outcome = puzzle.step(PuzzleAction::Continue);
+                    let next_state = match outcome.moved_to {
+                        Some(moved_to) => {
+                            *grid_coords = moved_to;
+                            MovementState::from_puzzle(puzzle, direction, &outcome)
+                        }
+                        None => MovementState::Idle,
+                    };
+                    puzzle_stepped.send(PuzzleStepped(outcome));
+                    next_state
                 }
             };
         }
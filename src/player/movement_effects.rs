@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
 
 use bevy::prelude::*;
 
-use crate::actions::MovementDirection;
+use crate::{actions::MovementDirection, levels::CurrentPuzzle};
 
-use super::movement::MovementState;
+use super::Player;
 
 #[derive(Debug, Default, Clone, Component, Eq, PartialEq)]
 pub struct MovementSideEffects(HashMap<MovementDirection, SideEffect>);
@@ -19,7 +22,15 @@ impl MovementSideEffects {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+impl Hash for MovementSideEffects {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for direction in MovementDirection::all() {
+            self.get(direction).hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum SideEffect {
     #[default]
     None,
@@ -27,21 +38,17 @@ pub enum SideEffect {
     MultiMove(u32),
 }
 
-impl SideEffect {
-    pub fn transform_movement_state(&self, movement_state: MovementState) -> MovementState {
-        match self {
-            SideEffect::None => movement_state,
-            SideEffect::DisabledMovement => MovementState::Idle,
-            SideEffect::MultiMove(count) => match movement_state {
-                MovementState::MultiMoving { direction, left } => {
-                    MovementState::MultiMoving { direction, left }
-                }
-                MovementState::Moving(direction) => MovementState::MultiMoving {
-                    direction,
-                    left: *count,
-                },
-                MovementState::Idle => MovementState::Idle,
-            },
+pub fn sync_side_effects_with_puzzle(
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut side_effects_q: Query<&mut MovementSideEffects, With<Player>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    for mut side_effects in side_effects_q.iter_mut() {
+        if *side_effects != puzzle.side_effects {
+            *side_effects = puzzle.side_effects.clone();
         }
     }
 }
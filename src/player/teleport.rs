@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+use bevy_ecs_tilemap::prelude::TilemapTileSize;
+
+use crate::{levels::CurrentPuzzle, puzzle::PuzzleStatus};
+
+use super::{
+    death::Dying,
+    movement::{MovementState, TweenTranslation},
+    Player,
+};
+
+const TELEPORT_DURATION: Duration = Duration::from_millis(400);
+
+/// Time spent warping so far, the player shrinks away and reappears at the partner pad.
+#[derive(Component, Default)]
+pub struct TeleportAnimation {
+    elapsed: Duration,
+}
+
+/// Plays the warp once the player has walked onto the pad. A player that lands on a closed
+/// laser only starts dying once they have reappeared.
+#[allow(clippy::type_complexity)]
+pub fn teleport_player(
+    mut commands: Commands,
+    mut player_q: Query<
+        (
+            Entity,
+            &mut MovementState,
+            &mut GridCoords,
+            &mut Transform,
+            Option<&mut TeleportAnimation>,
+        ),
+        (With<Player>, Without<TweenTranslation>, Without<Dying>),
+    >,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    tile_size_q: Query<&TilemapTileSize>,
+    time: Res<Time>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    let Some(tile_size) = tile_size_q.iter().next() else { return; };
+    for (entity, mut movement_state, mut coords, mut transform, animation) in player_q.iter_mut() {
+        if !matches!(*movement_state, MovementState::Teleporting(_)) {
+            continue;
+        }
+        let Some(mut animation) = animation else {
+            commands.entity(entity).insert(TeleportAnimation::default());
+            continue;
+        };
+        animation.elapsed += time.delta();
+        let t = (animation.elapsed.as_secs_f32() / TELEPORT_DURATION.as_secs_f32()).min(1.);
+        if t >= 0.5 && *coords != puzzle.player {
+            *coords = puzzle.player;
+            transform.translation = Vec3::new(
+                coords.x as f32 * tile_size.x + tile_size.x / 2.0,
+                coords.y as f32 * tile_size.y + tile_size.y / 2.0 + 8.,
+                transform.translation.z,
+            );
+        }
+        // Squeezed into a line on the way out, stretched back on the way in.
+        let squeeze = (2. * t - 1.).abs();
+        transform.scale = Vec3::new(squeeze, 2. - squeeze, 1.);
+        if t >= 1. {
+            transform.scale = Vec3::ONE;
+            *movement_state = MovementState::Idle;
+            commands.entity(entity).remove::<TeleportAnimation>();
+            if puzzle.status == PuzzleStatus::Dead {
+                commands.entity(entity).insert(Dying);
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+
+use crate::{
+    actions::Actions,
+    levels::{CurrentPuzzle, MoveCounter, PuzzleHistory, TurnSnapshot},
+    puzzle::PuzzleStatus,
+};
+
+use super::{death::Dying, movement::TweenTranslation, Player};
+
+/// Rewinds or replays whole turns. Plates, lasers, side effects and the player's color follow
+/// the restored [`CurrentPuzzle`] through their own sync systems.
+#[allow(clippy::type_complexity)]
+pub fn undo_redo_from_action(
+    mut actions: EventReader<Actions>,
+    mut player_q: Query<&mut GridCoords, (With<Player>, Without<Dying>, Without<TweenTranslation>)>,
+    mut puzzle: Option<ResMut<CurrentPuzzle>>,
+    mut history: ResMut<PuzzleHistory>,
+    mut moves: ResMut<MoveCounter>,
+) {
+    let Some(puzzle) = puzzle.as_mut() else { return; };
+    for action in actions.iter() {
+        if action.undo.is_none() && action.redo.is_none() {
+            continue;
+        }
+        let Ok(mut coords) = player_q.get_single_mut() else { continue; };
+        if puzzle.status != PuzzleStatus::Playing || puzzle.is_moving() {
+            continue;
+        }
+        let current = TurnSnapshot {
+            puzzle: puzzle.0.clone(),
+            moves: *moves,
+        };
+        let restored = if action.undo.is_some() {
+            history.undo(&current)
+        } else {
+            history.redo(&current)
+        };
+        let Some(restored) = restored else { continue; };
+        if *coords != restored.puzzle.player {
+            *coords = restored.puzzle.player;
+        }
+        puzzle.0 = restored.puzzle;
+        *moves = restored.moves;
+    }
+}
@@ -0,0 +1,90 @@
+/// Undo and redo stacks of turn snapshots.
+///
+/// A snapshot is taken at the start of every turn, i.e. before a player input that
+/// isn't part of a multi-move, so undoing rewinds a whole multi-move at once.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> History<T> {
+    /// Records `snapshot` as the state before a new turn. Clears what could be redone.
+    pub fn record(&mut self, snapshot: T) {
+        self.undo.push(snapshot);
+        self.redo.clear();
+    }
+
+    /// Returns the state to rewind to, remembering `current` for [`History::redo`].
+    pub fn undo(&mut self, current: &T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current.clone());
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: &T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.clone());
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = History::default();
+        history.record(0);
+        history.record(1);
+        assert_eq!(history.undo(&2), Some(1));
+        assert_eq!(history.undo(&1), Some(0));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(&0), Some(1));
+        assert_eq!(history.redo(&1), Some(2));
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(&2), Some(1));
+    }
+
+    #[test]
+    fn recording_after_an_undo_clears_redo() {
+        let mut history = History::default();
+        history.record(0);
+        assert_eq!(history.undo(&1), Some(0));
+        assert!(history.can_redo());
+        history.record(0);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(&2), None);
+    }
+
+    #[test]
+    fn nothing_to_undo_or_redo_when_empty() {
+        let mut history = History::<u32>::default();
+        assert_eq!(history.undo(&0), None);
+        assert_eq!(history.redo(&0), None);
+        assert!(!history.can_undo() && !history.can_redo());
+    }
+}
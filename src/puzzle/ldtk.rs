@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, FieldInstance, FieldValue, LayerInstance, Level},
+    GridCoords, LdtkLevel,
+};
+
+use crate::{
+    actions::MovementDirection, levels::tiles::LaserType, player::color_control::ColorControl,
+};
+
+use super::{
+    Cell, Emitter, Finish, Gate, GateLogic, Key, LaserSchedule, Mirror, MirrorOrientation, Plate,
+    PlateMode, PuzzleError, PuzzleLayout, PuzzleState, Teleporter, Wire,
+};
+
+impl PuzzleState {
+    pub fn from_ldtk_level(ldtk_level: &LdtkLevel) -> Result<Self, PuzzleError> {
+        Self::from_level(&ldtk_level.level)
+    }
+
+    pub fn from_level(level: &Level) -> Result<Self, PuzzleError> {
+        PuzzleLayout::from_level(level).map(PuzzleState::new)
+    }
+}
+
+impl PuzzleLayout {
+    pub fn from_level(level: &Level) -> Result<Self, PuzzleError> {
+        let int_grid = find_layer(level, "IntGrid").ok_or(PuzzleError::MissingLayer("IntGrid"))?;
+        let entities =
+            find_layer(level, "Entities").ok_or(PuzzleError::MissingLayer("Entities"))?;
+        let width = int_grid.c_wid;
+        let height = int_grid.c_hei;
+
+        let mut cells = vec![Cell::Empty; (width * height) as usize];
+        for (index, value) in int_grid.int_grid_csv.iter().enumerate() {
+            let index = index as i32;
+            let coords = ldtk_grid_to_grid_coords(index % width, index / width, height);
+            let cell =
+                Cell::from_int_grid_value(*value).ok_or(PuzzleError::InvalidIntGridValue {
+                    coords,
+                    value: *value,
+                })?;
+            cells[(coords.y * width + coords.x) as usize] = cell;
+        }
+
+        let mut start = None;
+        let mut plates = Vec::new();
+        let mut finishes = Vec::new();
+        let mut crates = Vec::new();
+        let mut teleporters = Vec::new();
+        let mut partner_iids = Vec::new();
+        let mut keys = Vec::new();
+        let mut gates = Vec::new();
+        let mut laser_schedules = Vec::new();
+        let mut emitters = Vec::new();
+        let mut mirrors = Vec::new();
+        let mut plate_wire_iids = Vec::new();
+        let mut gate_output_iids = Vec::new();
+        let mut wire_targets = HashMap::new();
+        let mut entity_coords = HashMap::new();
+        for entity_instance in entities.entity_instances.iter() {
+            let coords =
+                ldtk_grid_to_grid_coords(entity_instance.grid.x, entity_instance.grid.y, height);
+            entity_coords.insert(entity_instance.iid.as_str(), coords);
+            match entity_instance.identifier.as_str() {
+                "Player" => start = Some(coords),
+                "Panel" => {
+                    let fields = &entity_instance.field_instances;
+                    plates.push(plate_from_entity_instance(entity_instance, coords));
+                    plate_wire_iids.push(entity_refs_field(fields, "Wires"));
+                }
+                "Laser" => {
+                    let fields = &entity_instance.field_instances;
+                    wire_targets.insert(entity_instance.iid.as_str(), Wire::Laser(coords));
+                    laser_schedules.extend(laser_schedule(fields, coords));
+                }
+                "Gate" => {
+                    let fields = &entity_instance.field_instances;
+                    wire_targets.insert(entity_instance.iid.as_str(), Wire::Gate(gates.len()));
+                    gates.push(Gate {
+                        coords,
+                        logic: gate_logic(fields),
+                        outputs: Vec::new(),
+                    });
+                    gate_output_iids.push(entity_refs_field(fields, "Outputs"));
+                }
+                "Finish" => finishes.push(finish_from_entity_instance(entity_instance, coords)),
+                "Crate" => crates.push(coords),
+                "Teleporter" => {
+                    let fields = &entity_instance.field_instances;
+                    teleporters.push(Teleporter {
+                        coords,
+                        partner: None,
+                        color: enum_field(fields, "Color")
+                            .and_then(|color| color_from_enum(&color)),
+                    });
+                    partner_iids.push(entity_ref_field(fields, "Partner"));
+                }
+                "Key" => keys.push(Key {
+                    coords,
+                    color: enum_field(&entity_instance.field_instances, "Color")
+                        .and_then(|color| color_from_enum(&color))
+                        .unwrap_or_default(),
+                }),
+                "Emitter" => {
+                    let fields = &entity_instance.field_instances;
+                    emitters.push(Emitter {
+                        coords,
+                        direction: enum_field(fields, "Direction")
+                            .and_then(|direction| direction_from_wasd(&direction))
+                            .unwrap_or(MovementDirection::Right),
+                        laser_type: enum_field(fields, "Color")
+                            .and_then(|color| laser_type_from_enum(&color))
+                            .unwrap_or_default(),
+                    });
+                }
+                "Mirror" => {
+                    let fields = &entity_instance.field_instances;
+                    mirrors.push(Mirror {
+                        coords,
+                        orientation: mirror_orientation(fields),
+                        rotatable: bool_field(fields, "Rotatable").unwrap_or(false),
+                        pushable: bool_field(fields, "Pushable").unwrap_or(false),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // Partners can only be looked up once every entity has been seen.
+        for (teleporter, partner_iid) in teleporters.iter_mut().zip(partner_iids) {
+            teleporter.partner =
+                partner_iid.and_then(|iid| entity_coords.get(iid.as_str()).copied());
+        }
+        let resolve_wires = |iids: Vec<String>| -> Vec<Wire> {
+            iids.iter()
+                .filter_map(|iid| wire_targets.get(iid.as_str()).copied())
+                .collect()
+        };
+        for (plate, iids) in plates.iter_mut().zip(plate_wire_iids) {
+            plate.wires = resolve_wires(iids);
+            // Wired plates only reach the lasers they are wired to.
+            if !plate.wires.is_empty() {
+                plate.opens_laser = None;
+            }
+        }
+        for (gate, iids) in gates.iter_mut().zip(gate_output_iids) {
+            gate.outputs = resolve_wires(iids);
+        }
+
+        let layout = Self {
+            iid: level.iid.clone(),
+            identifier: level.identifier.clone(),
+            width,
+            height,
+            cells,
+            plates,
+            finishes,
+            start: start.ok_or(PuzzleError::MissingPlayer)?,
+            crates,
+            teleporters,
+            keys,
+            gates,
+            laser_schedules,
+            emitters,
+            mirrors,
+            colors: color_cycle(&level.field_instances),
+            par: int_field(&level.field_instances, "Par").map(|par| par as u32),
+        };
+        if layout.clock_period().is_none() {
+            return Err(PuzzleError::ClockPeriodTooLong);
+        }
+        Ok(layout)
+    }
+}
+
+/// IIDs the finishes of `level` lead to, in entity order.
+pub fn next_level_iids(level: &Level) -> Vec<String> {
+    let Some(entities) = find_layer(level, "Entities") else { return Vec::new(); };
+    entities
+        .entity_instances
+        .iter()
+        .filter(|entity_instance| entity_instance.identifier == "Finish")
+        .filter_map(|entity_instance| {
+            finish_from_entity_instance(entity_instance, GridCoords::default()).next_level
+        })
+        .collect()
+}
+
+/// Indices of `levels` in play order: the `NextLevel` chain from the first level, depth first,
+/// followed by levels it never reaches in project order.
+pub fn level_chain_order(levels: &[Level]) -> Vec<usize> {
+    let mut order = Vec::with_capacity(levels.len());
+    let mut visited = vec![false; levels.len()];
+    let mut stack = if levels.is_empty() { vec![] } else { vec![0] };
+    while let Some(index) = stack.pop() {
+        if visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        order.push(index);
+        for iid in next_level_iids(&levels[index]).iter().rev() {
+            if let Some(next) = levels.iter().position(|level| level.iid == *iid) {
+                stack.push(next);
+            }
+        }
+    }
+    order.extend((0..levels.len()).filter(|index| !visited[*index]));
+    order
+}
+
+pub fn find_layer<'a>(level: &'a Level, identifier: &str) -> Option<&'a LayerInstance> {
+    level
+        .layer_instances
+        .as_ref()?
+        .iter()
+        .find(|layer| layer.identifier == identifier)
+}
+
+/// LDtk counts rows from the top, `GridCoords` from the bottom.
+pub fn ldtk_grid_to_grid_coords(x: i32, y: i32, height: i32) -> GridCoords {
+    GridCoords {
+        x,
+        y: height - y - 1,
+    }
+}
+
+pub fn field<'a>(field_instances: &'a [FieldInstance], identifier: &str) -> Option<&'a FieldValue> {
+    field_instances
+        .iter()
+        .find(|field| field.identifier == identifier)
+        .map(|field| &field.value)
+}
+
+pub fn string_field(field_instances: &[FieldInstance], identifier: &str) -> Option<String> {
+    match field(field_instances, identifier)? {
+        FieldValue::String(Some(value)) => Some(value.to_owned()),
+        _ => None,
+    }
+}
+
+pub fn int_field(field_instances: &[FieldInstance], identifier: &str) -> Option<i32> {
+    match field(field_instances, identifier)? {
+        FieldValue::Int(value) => *value,
+        _ => None,
+    }
+}
+
+pub fn bool_field(field_instances: &[FieldInstance], identifier: &str) -> Option<bool> {
+    match field(field_instances, identifier)? {
+        FieldValue::Bool(value) => Some(*value),
+        _ => None,
+    }
+}
+
+pub fn enum_field(field_instances: &[FieldInstance], identifier: &str) -> Option<String> {
+    match field(field_instances, identifier)? {
+        FieldValue::Enum(Some(value)) => Some(value.to_owned()),
+        _ => None,
+    }
+}
+
+/// IID of the entity an entity reference field points to.
+pub fn entity_ref_field(field_instances: &[FieldInstance], identifier: &str) -> Option<String> {
+    match field(field_instances, identifier)? {
+        FieldValue::EntityRef(Some(entity_ref)) => Some(entity_ref.entity_iid.clone()),
+        _ => None,
+    }
+}
+
+/// IIDs of the entities an array of entity references points to.
+pub fn entity_refs_field(field_instances: &[FieldInstance], identifier: &str) -> Vec<String> {
+    match field(field_instances, identifier) {
+        Some(FieldValue::EntityRefs(entity_refs)) => entity_refs
+            .iter()
+            .flatten()
+            .map(|entity_ref| entity_ref.entity_iid.clone())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn enums_field(field_instances: &[FieldInstance], identifier: &str) -> Vec<String> {
+    match field(field_instances, identifier) {
+        Some(FieldValue::Enums(values)) => values.iter().flatten().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn ints_field(field_instances: &[FieldInstance], identifier: &str) -> Vec<i32> {
+    match field(field_instances, identifier) {
+        Some(FieldValue::Ints(values)) => values.iter().flatten().copied().collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn laser_type_from_enum(value: &str) -> Option<LaserType> {
+    match value {
+        "Red" => Some(LaserType::Red),
+        "Green" => Some(LaserType::Green),
+        "Blue" => Some(LaserType::Blue),
+        "Yellow" => Some(LaserType::Yellow),
+        "Cyan" => Some(LaserType::Cyan),
+        "Magenta" => Some(LaserType::Magenta),
+        "White" => Some(LaserType::White),
+        _ => None,
+    }
+}
+
+pub fn color_from_enum(value: &str) -> Option<ColorControl> {
+    match value {
+        "Red" => Some(ColorControl::Red),
+        "Green" => Some(ColorControl::Green),
+        "Blue" => Some(ColorControl::Blue),
+        _ => None,
+    }
+}
+
+/// Player colors from the level's `Colors` field, Blue then Red when it is left empty.
+pub fn color_cycle(field_instances: &[FieldInstance]) -> Vec<ColorControl> {
+    let mut colors: Vec<ColorControl> = Vec::new();
+    for color in enums_field(field_instances, "Colors")
+        .iter()
+        .filter_map(|color| color_from_enum(color))
+    {
+        if !colors.contains(&color) {
+            colors.push(color);
+        }
+    }
+    if colors.is_empty() {
+        colors = vec![ColorControl::Blue, ColorControl::Red];
+    }
+    colors
+}
+
+pub fn direction_from_wasd(value: &str) -> Option<MovementDirection> {
+    match value {
+        "W" => Some(MovementDirection::Up),
+        "S" => Some(MovementDirection::Down),
+        "A" => Some(MovementDirection::Left),
+        "D" => Some(MovementDirection::Right),
+        _ => None,
+    }
+}
+
+/// Reads the plate's `Mode` field, `Timed_Turns` only matters for timed plates.
+pub fn plate_mode(field_instances: &[FieldInstance]) -> PlateMode {
+    match enum_field(field_instances, "Mode").as_deref() {
+        Some("Toggle") => PlateMode::Toggle,
+        Some("Hold") => PlateMode::Hold,
+        Some("Timed") => PlateMode::Timed(
+            int_field(field_instances, "Timed_Turns")
+                .unwrap_or(1)
+                .max(1) as u32,
+        ),
+        _ => PlateMode::Latch,
+    }
+}
+
+/// Reads `Open_Turns`, `Closed_Turns` and `Turn_Offset`, lasers without both turn counts
+/// aren't timed.
+pub fn laser_schedule(
+    field_instances: &[FieldInstance],
+    coords: GridCoords,
+) -> Option<LaserSchedule> {
+    let open_turns = int_field(field_instances, "Open_Turns").filter(|turns| *turns > 0)?;
+    let closed_turns = int_field(field_instances, "Closed_Turns").filter(|turns| *turns > 0)?;
+    let mut schedule = LaserSchedule {
+        coords,
+        open_turns: open_turns as u32,
+        closed_turns: closed_turns as u32,
+        offset: 0,
+    };
+    // Within one cycle, so adding the clock to it can't overflow.
+    schedule.offset = int_field(field_instances, "Turn_Offset")
+        .unwrap_or(0)
+        .max(0) as u32
+        % schedule.period();
+    Some(schedule)
+}
+
+pub fn gate_logic(field_instances: &[FieldInstance]) -> GateLogic {
+    match enum_field(field_instances, "Logic").as_deref() {
+        Some("Or") => GateLogic::Or,
+        Some("Not") => GateLogic::Not,
+        Some("Xor") => GateLogic::Xor,
+        _ => GateLogic::And,
+    }
+}
+
+pub fn mirror_orientation(field_instances: &[FieldInstance]) -> MirrorOrientation {
+    match enum_field(field_instances, "Orientation").as_deref() {
+        Some("Backslash") => MirrorOrientation::Backslash,
+        _ => MirrorOrientation::Slash,
+    }
+}
+
+pub fn plate_from_entity_instance(entity_instance: &EntityInstance, coords: GridCoords) -> Plate {
+    let fields = &entity_instance.field_instances;
+    let multi_movement = enums_field(fields, "Wasd_Multi_Move")
+        .iter()
+        .filter_map(|movement| direction_from_wasd(movement))
+        .zip(ints_field(fields, "Multi_Move_Values"))
+        .map(|(movement, count)| (movement, count.max(1) as u32))
+        .collect();
+    Plate {
+        coords,
+        opens_laser: enum_field(fields, "Door").and_then(|door| laser_type_from_enum(&door)),
+        mode: plate_mode(fields),
+        wires: Vec::new(),
+        forbids_movement: enums_field(fields, "Wasd_Disable")
+            .iter()
+            .filter_map(|movement| direction_from_wasd(movement))
+            .collect(),
+        multi_movement,
+    }
+}
+
+pub fn finish_from_entity_instance(entity_instance: &EntityInstance, coords: GridCoords) -> Finish {
+    let fields = &entity_instance.field_instances;
+    Finish {
+        coords,
+        next_level: string_field(fields, "NextLevel"),
+        message: string_field(fields, "Message"),
+    }
+}
@@ -0,0 +1,1837 @@
+//! Headless rules of the puzzle.
+//!
+//! Everything that decides what happens when the player moves or switches color
+//! lives here, independent of the ECS: a level is loaded into a [`PuzzleState`]
+//! and advanced with [`PuzzleState::step`]. Bevy systems only present the result.
+
+pub mod history;
+pub mod ldtk;
+pub mod pathfinding;
+pub mod solver;
+pub mod validation;
+
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use bevy_ecs_ldtk::GridCoords;
+
+use crate::{
+    actions::MovementDirection,
+    levels::tiles::LaserType,
+    player::{
+        color_control::ColorControl,
+        movement_effects::{MovementSideEffects, SideEffect},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Wall,
+    Floor,
+    Door(LaserType),
+    /// Floor the player keeps sliding over until they reach a tile that isn't ice.
+    Ice,
+    /// Carries a player that comes to rest on it one tile further along its direction.
+    Conveyor(MovementDirection),
+    /// Can only be entered while moving along its direction.
+    OneWay(MovementDirection),
+    /// Blocks like a wall until the player walks in holding a key of its color.
+    Lock(ColorControl),
+}
+
+impl Cell {
+    pub fn from_int_grid_value(value: i32) -> Option<Cell> {
+        match value {
+            0 => Some(Cell::Empty),
+            1 => Some(Cell::Wall),
+            2 => Some(Cell::Floor),
+            10 => Some(Cell::Ice),
+            11..=14 => Some(Cell::Conveyor(
+                MovementDirection::all()[(value - 11) as usize],
+            )),
+            15..=18 => Some(Cell::OneWay(
+                MovementDirection::all()[(value - 15) as usize],
+            )),
+            19 => Some(Cell::Lock(ColorControl::Red)),
+            20 => Some(Cell::Lock(ColorControl::Green)),
+            21 => Some(Cell::Lock(ColorControl::Blue)),
+            value => LaserType::from_int_grid_value(value).map(Cell::Door),
+        }
+    }
+}
+
+/// How a plate reacts to being stepped on and left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlateMode {
+    /// Stays active for the rest of the level.
+    #[default]
+    Latch,
+    /// Flips every time it is stepped on.
+    Toggle,
+    /// Active only while stood on.
+    Hold,
+    /// Stays active for this many turns after being left. Every step counts as a turn, color
+    /// switches and each tile of a multi-move included, the same as for the clock.
+    Timed(u32),
+}
+
+/// Where a plate or gate sends its signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wire {
+    /// The laser on the door tile at these coordinates.
+    Laser(GridCoords),
+    Gate(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GateLogic {
+    #[default]
+    And,
+    Or,
+    /// On while none of its inputs are, with several inputs it acts as a NOR.
+    Not,
+    /// On while an odd number of its inputs are.
+    Xor,
+}
+
+impl GateLogic {
+    pub fn combine(self, inputs: impl IntoIterator<Item = bool>) -> bool {
+        let (count, on) = inputs.into_iter().fold((0, 0), |(count, on), input| {
+            (count + 1, on + input as usize)
+        });
+        match self {
+            GateLogic::And => count > 0 && on == count,
+            GateLogic::Or => on > 0,
+            GateLogic::Not => on == 0,
+            GateLogic::Xor => on % 2 == 1,
+        }
+    }
+}
+
+/// Combines the signals of every plate and gate wired to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Gate {
+    pub coords: GridCoords,
+    pub logic: GateLogic,
+    pub outputs: Vec<Wire>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plate {
+    pub coords: GridCoords,
+    /// Color the plate opens everywhere, `None` for plates wired to specific lasers.
+    pub opens_laser: Option<LaserType>,
+    pub wires: Vec<Wire>,
+    pub mode: PlateMode,
+    pub forbids_movement: Vec<MovementDirection>,
+    pub multi_movement: Vec<(MovementDirection, u32)>,
+}
+
+impl Plate {
+    pub fn has_side_effects(&self) -> bool {
+        !self.forbids_movement.is_empty() || !self.multi_movement.is_empty()
+    }
+
+    /// Side effects come with pressing a plate, not with it being active: every press applies
+    /// them again, a toggle switching off included, and releasing the plate keeps them.
+    fn apply_side_effects(&self, side_effects: &mut MovementSideEffects) {
+        for movement in self.forbids_movement.iter() {
+            side_effects.set(*movement, SideEffect::DisabledMovement);
+        }
+        for (movement, count) in self.multi_movement.iter() {
+            side_effects.set(*movement, SideEffect::MultiMove(*count));
+        }
+    }
+}
+
+/// Pad that sends the player to its partner.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Teleporter {
+    pub coords: GridCoords,
+    /// `None` when the partner link is missing, the pad then does nothing.
+    pub partner: Option<GridCoords>,
+    /// Only teleports while the player has this color.
+    pub color: Option<ColorControl>,
+}
+
+/// Longest the timed lasers of a level may take to all line up again. Anything longer can't be
+/// planned around by the player, and would keep the solver from ever seeing a state twice.
+pub const MAX_CLOCK_PERIOD: u32 = 360;
+
+/// Turns a laser off and on again on the puzzle's turn clock, one turn per tile moved or
+/// color switch. Outside of its open turns the laser follows the usual color rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaserSchedule {
+    pub coords: GridCoords,
+    pub open_turns: u32,
+    pub closed_turns: u32,
+    /// Turns the cycle is ahead by when the level starts, less than [`Self::period`].
+    pub offset: u32,
+}
+
+impl LaserSchedule {
+    pub fn period(&self) -> u32 {
+        self.open_turns + self.closed_turns
+    }
+
+    pub fn is_open_at(&self, clock: u32) -> bool {
+        (clock + self.offset) % self.period() < self.open_turns
+    }
+}
+
+/// Casts a beam along `direction` until something blocks it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Emitter {
+    pub coords: GridCoords,
+    pub direction: MovementDirection,
+    pub laser_type: LaserType,
+}
+
+/// Which way a mirror's surface runs, as seen on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MirrorOrientation {
+    /// `/`, from bottom left to top right.
+    #[default]
+    Slash,
+    /// `\`, from top left to bottom right.
+    Backslash,
+}
+
+impl MirrorOrientation {
+    /// A quarter turn swaps the two diagonals.
+    pub fn rotated(self) -> Self {
+        match self {
+            MirrorOrientation::Slash => MirrorOrientation::Backslash,
+            MirrorOrientation::Backslash => MirrorOrientation::Slash,
+        }
+    }
+
+    /// Direction a beam travelling along `direction` leaves the mirror in.
+    pub fn deflect(self, direction: MovementDirection) -> MovementDirection {
+        match self {
+            MirrorOrientation::Slash => match direction {
+                MovementDirection::Up => MovementDirection::Right,
+                MovementDirection::Right => MovementDirection::Up,
+                MovementDirection::Down => MovementDirection::Left,
+                MovementDirection::Left => MovementDirection::Down,
+            },
+            MirrorOrientation::Backslash => match direction {
+                MovementDirection::Up => MovementDirection::Left,
+                MovementDirection::Left => MovementDirection::Up,
+                MovementDirection::Down => MovementDirection::Right,
+                MovementDirection::Right => MovementDirection::Down,
+            },
+        }
+    }
+}
+
+/// Turns beams by 90°. Blocks the player unless it can be pushed or rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Mirror {
+    pub coords: GridCoords,
+    pub orientation: MirrorOrientation,
+    /// Walking into it turns it a quarter, when it can't be pushed.
+    pub rotatable: bool,
+    /// Walking into it pushes it like a crate. Unlike crates, mirrors are too light to press
+    /// the plates they are pushed onto.
+    pub pushable: bool,
+}
+
+/// A tile a beam crosses, `direction` being the way it leaves the tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeamCell {
+    pub coords: GridCoords,
+    pub direction: MovementDirection,
+    pub laser_type: LaserType,
+}
+
+/// Key lying in the level, picked up by walking over it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Key {
+    pub coords: GridCoords,
+    pub color: ColorControl,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Finish {
+    pub coords: GridCoords,
+    pub next_level: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Static part of a level: everything that never changes while it is played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleLayout {
+    pub iid: String,
+    pub identifier: String,
+    pub width: i32,
+    pub height: i32,
+    cells: Vec<Cell>,
+    pub plates: Vec<Plate>,
+    pub finishes: Vec<Finish>,
+    pub start: GridCoords,
+    /// Where the crates start, the order matches [`PuzzleState::crates`].
+    pub crates: Vec<GridCoords>,
+    pub teleporters: Vec<Teleporter>,
+    /// Where the keys lie, the order matches [`PuzzleState::keys_taken`].
+    pub keys: Vec<Key>,
+    pub gates: Vec<Gate>,
+    pub laser_schedules: Vec<LaserSchedule>,
+    pub emitters: Vec<Emitter>,
+    /// How the mirrors start out, the order matches [`PuzzleState::mirrors`].
+    pub mirrors: Vec<Mirror>,
+    /// Colors the player switches through in order, starting with the first one.
+    pub colors: Vec<ColorControl>,
+    /// Moves and color switches of a good solution, as set in the level.
+    pub par: Option<u32>,
+}
+
+impl PuzzleLayout {
+    /// Returns `None` outside of the level bounds.
+    pub fn cell(&self, coords: GridCoords) -> Option<Cell> {
+        if coords.x < 0 || coords.y < 0 || coords.x >= self.width || coords.y >= self.height {
+            return None;
+        }
+        self.cells
+            .get((coords.y * self.width + coords.x) as usize)
+            .copied()
+    }
+
+    pub fn is_passable(&self, coords: GridCoords) -> bool {
+        !matches!(self.cell(coords), None | Some(Cell::Wall)) && self.emitter_at(coords).is_none()
+    }
+
+    /// Whether the player or a crate moving along `direction` may enter the tile at `coords`.
+    /// Emitters stand in the way like walls.
+    pub fn can_enter(&self, coords: GridCoords, direction: MovementDirection) -> bool {
+        if self.emitter_at(coords).is_some() {
+            return false;
+        }
+        match self.cell(coords) {
+            None | Some(Cell::Wall) => false,
+            Some(Cell::OneWay(allowed)) => allowed == direction,
+            _ => true,
+        }
+    }
+
+    pub fn plate_at(&self, coords: GridCoords) -> Option<usize> {
+        self.plates.iter().position(|plate| plate.coords == coords)
+    }
+
+    pub fn teleporter_at(&self, coords: GridCoords) -> Option<&Teleporter> {
+        self.teleporters
+            .iter()
+            .find(|teleporter| teleporter.coords == coords)
+    }
+
+    pub fn laser_schedule_at(&self, coords: GridCoords) -> Option<&LaserSchedule> {
+        self.laser_schedules
+            .iter()
+            .find(|schedule| schedule.coords == coords)
+    }
+
+    /// Turns after which every timed laser is back where it started. `None` when that takes
+    /// longer than [`MAX_CLOCK_PERIOD`], such levels fail to load with
+    /// [`PuzzleError::ClockPeriodTooLong`].
+    pub fn clock_period(&self) -> Option<u32> {
+        self.laser_schedules.iter().try_fold(1, |period, schedule| {
+            lcm(period, schedule.period()).filter(|period| *period <= MAX_CLOCK_PERIOD)
+        })
+    }
+
+    pub fn emitter_at(&self, coords: GridCoords) -> Option<&Emitter> {
+        self.emitters
+            .iter()
+            .find(|emitter| emitter.coords == coords)
+    }
+
+    pub fn key_at(&self, coords: GridCoords) -> Option<usize> {
+        self.keys.iter().position(|key| key.coords == coords)
+    }
+
+    pub fn finish_at(&self, coords: GridCoords) -> Option<usize> {
+        self.finishes
+            .iter()
+            .position(|finish| finish.coords == coords)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PuzzleAction {
+    Move(MovementDirection),
+    SwitchColor,
+    /// Advances a multi-move that is still in progress by one tile.
+    Continue,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PuzzleStatus {
+    #[default]
+    Playing,
+    Dead,
+    Finished(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Momentum {
+    pub direction: MovementDirection,
+    pub left: u32,
+}
+
+/// Everything a single [`PuzzleState::step`] did, for the presentation to replay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub moved_to: Option<GridCoords>,
+    /// Where a teleporter at `moved_to` sent the player.
+    pub teleported_to: Option<GridCoords>,
+    pub color_switched: bool,
+    /// Plates the player stepped on or a crate was pushed onto.
+    pub pressed_plates: Vec<usize>,
+    /// Plates that went inactive, from being left, running out or toggled off.
+    pub released_plates: Vec<usize>,
+    /// Key the player picked up at the tile they ended on.
+    pub picked_up_key: Option<usize>,
+    /// Lock the player used a key on to walk in.
+    pub unlocked: Option<GridCoords>,
+    /// Mirror the player turned by walking into it.
+    pub rotated_mirror: Option<usize>,
+    pub died: bool,
+    pub finished: Option<usize>,
+}
+
+impl StepOutcome {
+    pub fn is_noop(&self) -> bool {
+        *self == StepOutcome::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    MissingLayer(&'static str),
+    MissingPlayer,
+    InvalidIntGridValue { coords: GridCoords, value: i32 },
+    ClockPeriodTooLong,
+}
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleError::MissingLayer(identifier) => write!(f, "missing layer {}", identifier),
+            PuzzleError::MissingPlayer => write!(f, "missing Player entity"),
+            PuzzleError::InvalidIntGridValue { coords, value } => write!(
+                f,
+                "invalid IntGrid value {} at ({}, {})",
+                value, coords.x, coords.y
+            ),
+            PuzzleError::ClockPeriodTooLong => write!(
+                f,
+                "timed lasers take more than {} turns to line up again",
+                MAX_CLOCK_PERIOD
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleState {
+    layout: Arc<PuzzleLayout>,
+    pub player: GridCoords,
+    pub color: ColorControl,
+    pub crates: Vec<GridCoords>,
+    pub mirrors: Vec<Mirror>,
+    pub side_effects: MovementSideEffects,
+    pub active_plates: Vec<bool>,
+    /// Turns left before each timed plate goes inactive again.
+    pub plate_timers: Vec<u32>,
+    pub keys_taken: Vec<bool>,
+    /// Colors of the keys the player holds, in the order they were picked up.
+    pub inventory: Vec<ColorControl>,
+    pub unlocked: Vec<GridCoords>,
+    /// Turns taken, wrapped around [`PuzzleLayout::clock_period`].
+    pub clock: u32,
+    pub momentum: Option<Momentum>,
+    pub status: PuzzleStatus,
+    /// Tiles the beams cross, traced again whenever a step moves a crate or mirror or opens a
+    /// lock. Follows from the fields above, so it isn't hashed.
+    beams: Vec<BeamCell>,
+}
+
+/// Only the dynamic part is hashed: states are compared within a single level.
+impl Hash for PuzzleState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.player.hash(state);
+        self.color.hash(state);
+        self.crates.hash(state);
+        self.mirrors.hash(state);
+        self.side_effects.hash(state);
+        self.active_plates.hash(state);
+        self.plate_timers.hash(state);
+        self.keys_taken.hash(state);
+        self.inventory.hash(state);
+        self.unlocked.hash(state);
+        self.clock.hash(state);
+        self.momentum.hash(state);
+        self.status.hash(state);
+    }
+}
+
+impl PuzzleState {
+    pub fn new(layout: PuzzleLayout) -> Self {
+        let mut state = Self {
+            player: layout.start,
+            crates: layout.crates.clone(),
+            mirrors: layout.mirrors.clone(),
+            color: layout.colors.first().copied().unwrap_or_default(),
+            side_effects: MovementSideEffects::default(),
+            active_plates: vec![false; layout.plates.len()],
+            plate_timers: vec![0; layout.plates.len()],
+            keys_taken: vec![false; layout.keys.len()],
+            inventory: Vec::new(),
+            unlocked: Vec::new(),
+            clock: 0,
+            momentum: None,
+            status: PuzzleStatus::Playing,
+            layout: Arc::new(layout),
+            beams: Vec::new(),
+        };
+        // Plates the player or a crate starts out on are held down from the first turn.
+        let mut outcome = StepOutcome::default();
+        for index in 0..state.layout.plates.len() {
+            let coords = state.layout.plates[index].coords;
+            if coords == state.player || state.crates.contains(&coords) {
+                state.press_plate(index, &mut outcome);
+            }
+        }
+        state.trace_beams();
+        state
+    }
+
+    pub fn layout(&self) -> &PuzzleLayout {
+        &self.layout
+    }
+
+    pub fn is_plate_active(&self, index: usize) -> bool {
+        self.active_plates.get(index).copied().unwrap_or(false)
+    }
+
+    /// Primaries that are on right now, from the player's color and every active plate.
+    pub fn lit_primaries(&self) -> u8 {
+        self.layout
+            .plates
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.is_plate_active(*index))
+            .filter_map(|(_, plate)| plate.opens_laser)
+            .fold(self.color.as_laser_type().bits(), |primaries, laser| {
+                primaries | laser.bits()
+            })
+    }
+
+    pub fn is_laser_open(&self, laser_type: LaserType) -> bool {
+        laser_type.is_lit_by(self.lit_primaries())
+    }
+
+    /// Whether the gate's output is on. Gates wired into a loop read as off inside it.
+    pub fn is_gate_on(&self, index: usize) -> bool {
+        self.gate_signal(index, &mut Vec::new())
+    }
+
+    fn gate_signal(&self, index: usize, visiting: &mut Vec<usize>) -> bool {
+        if visiting.contains(&index) {
+            return false;
+        }
+        visiting.push(index);
+        let target = Wire::Gate(index);
+        let mut inputs: Vec<bool> = self
+            .layout
+            .plates
+            .iter()
+            .enumerate()
+            .filter(|(_, plate)| plate.wires.contains(&target))
+            .map(|(plate_index, _)| self.is_plate_active(plate_index))
+            .collect();
+        for (gate_index, gate) in self.layout.gates.iter().enumerate() {
+            if gate.outputs.contains(&target) {
+                inputs.push(self.gate_signal(gate_index, visiting));
+            }
+        }
+        visiting.pop();
+        self.layout.gates[index].logic.combine(inputs)
+    }
+
+    /// Whether a circuit powers the laser at `coords`, `None` when no wire leads to it.
+    pub fn wired_signal(&self, coords: GridCoords) -> Option<bool> {
+        let target = Wire::Laser(coords);
+        let plates = self
+            .layout
+            .plates
+            .iter()
+            .enumerate()
+            .filter(|(_, plate)| plate.wires.contains(&target))
+            .map(|(index, _)| self.is_plate_active(index));
+        let gates = self
+            .layout
+            .gates
+            .iter()
+            .enumerate()
+            .filter(|(_, gate)| gate.outputs.contains(&target))
+            .map(|(index, _)| self.is_gate_on(index));
+        plates
+            .chain(gates)
+            .reduce(|powered, signal| powered || signal)
+    }
+
+    /// Whether the tile can be stood on without dying: its door is open and every beam
+    /// crossing it is of a color that is lit.
+    pub fn is_open_at(&self, coords: GridCoords) -> bool {
+        self.is_door_open_at(coords)
+            && self
+                .beams
+                .iter()
+                .filter(|beam| beam.coords == coords)
+                .all(|beam| self.is_laser_open(beam.laser_type))
+    }
+
+    /// Whether the door laser on the tile is open, beams aside. Tiles without a door are always
+    /// open. Wired lasers open for their circuit and the player's own color, plates of their
+    /// color elsewhere in the level don't reach them.
+    pub fn is_door_open_at(&self, coords: GridCoords) -> bool {
+        let scheduled_open = self
+            .layout
+            .laser_schedule_at(coords)
+            .is_some_and(|schedule| schedule.is_open_at(self.clock));
+        match self.layout.cell(coords) {
+            Some(Cell::Door(_)) if scheduled_open => true,
+            Some(Cell::Door(laser_type)) => match self.wired_signal(coords) {
+                Some(powered) => powered || laser_type.is_lit_by(self.color.as_laser_type().bits()),
+                None => self.is_laser_open(laser_type),
+            },
+            _ => true,
+        }
+    }
+
+    /// Every tile a beam crosses, in the order the beams travel.
+    pub fn beam_cells(&self) -> &[BeamCell] {
+        &self.beams
+    }
+
+    /// Traces every emitter's beam from the tile in front of it. Beams pass over floor and
+    /// doors, turn at mirrors and stop at walls, closed locks, emitters and crates. Mirrors
+    /// only ever turn a beam, so it can't loop without running into its own emitter.
+    fn trace_beams(&mut self) {
+        let mut cells = Vec::new();
+        for emitter in self.layout.emitters.iter() {
+            let mut coords = emitter.coords;
+            let mut direction = emitter.direction;
+            loop {
+                let offset = direction.as_ivec2();
+                coords = GridCoords {
+                    x: coords.x + offset.x,
+                    y: coords.y + offset.y,
+                };
+                if !self.layout.is_passable(coords)
+                    || self.closed_lock_at(coords).is_some()
+                    || self.crate_at(coords).is_some()
+                {
+                    break;
+                }
+                if let Some(index) = self.mirror_at(coords) {
+                    direction = self.mirrors[index].orientation.deflect(direction);
+                }
+                cells.push(BeamCell {
+                    coords,
+                    direction,
+                    laser_type: emitter.laser_type,
+                });
+            }
+        }
+        self.beams = cells;
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.momentum.is_some()
+    }
+
+    pub fn crate_at(&self, coords: GridCoords) -> Option<usize> {
+        self.crates
+            .iter()
+            .position(|crate_coords| *crate_coords == coords)
+    }
+
+    pub fn mirror_at(&self, coords: GridCoords) -> Option<usize> {
+        self.mirrors
+            .iter()
+            .position(|mirror| mirror.coords == coords)
+    }
+
+    /// Partner of the pad at `coords`, if it works for the player's color and isn't blocked by
+    /// a crate or mirror.
+    fn teleport_destination(&self, coords: GridCoords) -> Option<GridCoords> {
+        let teleporter = self.layout.teleporter_at(coords)?;
+        if teleporter.color.is_some_and(|color| color != self.color) {
+            return None;
+        }
+        teleporter.partner.filter(|partner| {
+            self.crate_at(*partner).is_none() && self.mirror_at(*partner).is_none()
+        })
+    }
+
+    /// Color of the lock at `coords`, unless it has been opened already.
+    pub fn closed_lock_at(&self, coords: GridCoords) -> Option<ColorControl> {
+        match self.layout.cell(coords) {
+            Some(Cell::Lock(color)) if !self.unlocked.contains(&coords) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// Crates can be pushed onto any free tile the player could survive on.
+    fn can_take_crate(&self, coords: GridCoords, direction: MovementDirection) -> bool {
+        self.layout.can_enter(coords, direction)
+            && self.closed_lock_at(coords).is_none()
+            && self.crate_at(coords).is_none()
+            && self.mirror_at(coords).is_none()
+            && self.is_open_at(coords)
+    }
+
+    /// Applies one action. Actions that are not possible right now are ignored
+    /// and produce an empty outcome.
+    pub fn step(&mut self, action: PuzzleAction) -> StepOutcome {
+        let mut outcome = StepOutcome::default();
+        if self.status != PuzzleStatus::Playing {
+            return outcome;
+        }
+        match action {
+            PuzzleAction::Move(direction) => {
+                if self.is_moving() {
+                    return outcome;
+                }
+                let left = match self.side_effects.get(direction) {
+                    SideEffect::None => 1,
+                    SideEffect::DisabledMovement => return outcome,
+                    SideEffect::MultiMove(count) => count.max(1),
+                };
+                self.momentum = Some(Momentum { direction, left });
+                self.advance(&mut outcome);
+            }
+            PuzzleAction::SwitchColor => {
+                let next = self.color.next_in(&self.layout.colors);
+                if next == self.color {
+                    return outcome;
+                }
+                self.color = next;
+                outcome.color_switched = true;
+                self.settle(&mut outcome);
+            }
+            PuzzleAction::Continue => self.advance(&mut outcome),
+        }
+        if !outcome.is_noop() && self.status == PuzzleStatus::Playing {
+            self.tick(&mut outcome);
+        }
+        outcome
+    }
+
+    /// Ends the turn: timed plates count down and timed lasers move on. A laser closing
+    /// under the player kills them, whether they stood still or just walked onto it.
+    fn tick(&mut self, outcome: &mut StepOutcome) {
+        let plates_released = self.tick_plates(outcome);
+        let clock_moved = self.tick_clock();
+        if plates_released || clock_moved {
+            self.settle(outcome);
+        }
+    }
+
+    /// Counts timed plates nothing stands on down by one turn, returns whether any ran out.
+    fn tick_plates(&mut self, outcome: &mut StepOutcome) -> bool {
+        let mut released = false;
+        for (index, plate) in self.layout.plates.iter().enumerate() {
+            if !matches!(plate.mode, PlateMode::Timed(_)) || !self.active_plates[index] {
+                continue;
+            }
+            if plate.coords == self.player || self.crates.contains(&plate.coords) {
+                continue;
+            }
+            self.plate_timers[index] = self.plate_timers[index].saturating_sub(1);
+            if self.plate_timers[index] == 0 {
+                self.active_plates[index] = false;
+                outcome.released_plates.push(index);
+                released = true;
+            }
+        }
+        released
+    }
+
+    /// Moves timed lasers on by one turn, returns whether there are any.
+    fn tick_clock(&mut self) -> bool {
+        if self.layout.laser_schedules.is_empty() {
+            return false;
+        }
+        let period = self.layout.clock_period().unwrap_or(u32::MAX);
+        self.clock = (self.clock + 1) % period;
+        true
+    }
+
+    fn advance(&mut self, outcome: &mut StepOutcome) {
+        let Some(mut momentum) = self.momentum.take() else { return; };
+        let offset = momentum.direction.as_ivec2();
+        let next = GridCoords {
+            x: self.player.x + offset.x,
+            y: self.player.y + offset.y,
+        };
+        if !self.layout.can_enter(next, momentum.direction) {
+            return;
+        }
+        let lock = self.closed_lock_at(next);
+        if lock.is_some_and(|color| !self.inventory.contains(&color)) {
+            return;
+        }
+        if let Some(index) = self.mirror_at(next) {
+            let mirror = self.mirrors[index];
+            let mirror_next = GridCoords {
+                x: next.x + offset.x,
+                y: next.y + offset.y,
+            };
+            if mirror.pushable && self.can_take_crate(mirror_next, momentum.direction) {
+                self.mirrors[index].coords = mirror_next;
+                self.trace_beams();
+            } else {
+                // Turning a mirror takes the player's move, they stay where they are.
+                if mirror.rotatable {
+                    self.mirrors[index].orientation = mirror.orientation.rotated();
+                    outcome.rotated_mirror = Some(index);
+                    self.trace_beams();
+                    self.settle(outcome);
+                }
+                return;
+            }
+        }
+        let pushed_crate = self.crate_at(next);
+        if let Some(crate_index) = pushed_crate {
+            let crate_next = GridCoords {
+                x: next.x + offset.x,
+                y: next.y + offset.y,
+            };
+            if !self.can_take_crate(crate_next, momentum.direction) {
+                return;
+            }
+            self.crates[crate_index] = crate_next;
+            self.trace_beams();
+        }
+        if let Some(color) = lock {
+            if let Some(held) = self.inventory.iter().position(|key| *key == color) {
+                self.inventory.remove(held);
+            }
+            self.unlocked.push(next);
+            self.trace_beams();
+            outcome.unlocked = Some(next);
+        }
+        self.player = next;
+        outcome.moved_to = Some(next);
+        momentum.left -= 1;
+        if self.layout.cell(next) == Some(Cell::Ice) {
+            momentum.left = momentum.left.max(1);
+        }
+        if momentum.left > 0 {
+            self.momentum = Some(momentum);
+        }
+        if let Some(destination) = self.teleport_destination(next) {
+            self.player = destination;
+            self.momentum = None;
+            outcome.teleported_to = Some(destination);
+        }
+        self.pick_up_key(outcome);
+        self.release_plates(outcome);
+        if let Some(index) = pushed_crate.and_then(|index| self.layout.plate_at(self.crates[index]))
+        {
+            self.press_plate(index, outcome);
+        }
+        if let Some(index) = self.layout.plate_at(self.player) {
+            self.press_plate(index, outcome);
+        }
+        self.settle(outcome);
+        // Pads hand the player over at rest, conveyors only pick up a player that walked on.
+        if outcome.teleported_to.is_none() {
+            self.ride_conveyor();
+        }
+    }
+
+    /// Gives a player resting on a conveyor one more tile of momentum. Conveyors leading onto
+    /// each other carry the player on, one tile per step, until one of them is blocked.
+    fn ride_conveyor(&mut self) {
+        if self.status != PuzzleStatus::Playing || self.is_moving() {
+            return;
+        }
+        if let Some(Cell::Conveyor(direction)) = self.layout.cell(self.player) {
+            self.momentum = Some(Momentum { direction, left: 1 });
+        }
+    }
+
+    fn pick_up_key(&mut self, outcome: &mut StepOutcome) {
+        let Some(index) = self.layout.key_at(self.player) else { return; };
+        if self.keys_taken[index] {
+            return;
+        }
+        self.keys_taken[index] = true;
+        self.inventory.push(self.layout.keys[index].color);
+        outcome.picked_up_key = Some(index);
+    }
+
+    /// Lets go of held plates nothing stands on anymore.
+    fn release_plates(&mut self, outcome: &mut StepOutcome) {
+        for (index, plate) in self.layout.plates.iter().enumerate() {
+            let occupied = plate.coords == self.player || self.crates.contains(&plate.coords);
+            if !self.active_plates[index] || occupied {
+                continue;
+            }
+            if plate.mode == PlateMode::Hold {
+                self.active_plates[index] = false;
+                outcome.released_plates.push(index);
+            }
+        }
+    }
+
+    fn press_plate(&mut self, index: usize, outcome: &mut StepOutcome) {
+        let plate = &self.layout.plates[index];
+        match plate.mode {
+            PlateMode::Latch | PlateMode::Hold => self.active_plates[index] = true,
+            PlateMode::Toggle => {
+                self.active_plates[index] = !self.active_plates[index];
+                if !self.active_plates[index] {
+                    outcome.released_plates.push(index);
+                }
+            }
+            PlateMode::Timed(turns) => {
+                self.active_plates[index] = true;
+                self.plate_timers[index] = turns.max(1);
+            }
+        }
+        plate.apply_side_effects(&mut self.side_effects);
+        outcome.pressed_plates.push(index);
+    }
+
+    fn settle(&mut self, outcome: &mut StepOutcome) {
+        if !self.is_open_at(self.player) {
+            self.status = PuzzleStatus::Dead;
+            self.momentum = None;
+            outcome.died = true;
+        } else if let Some(index) = self.layout.finish_at(self.player) {
+            self.status = PuzzleStatus::Finished(index);
+            self.momentum = None;
+            for direction in MovementDirection::all() {
+                self.side_effects
+                    .set(direction, SideEffect::DisabledMovement);
+            }
+            outcome.finished = Some(index);
+        }
+    }
+}
+
+/// `None` when the result overflows.
+fn lcm(a: u32, b: u32) -> Option<u32> {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    (a / x).checked_mul(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MovementDirection::*;
+
+    /// Builds a layout from rows drawn top to bottom: `#` wall, `.` floor, `@` start,
+    /// `F` finish, `P` plate, `C` crate, `I` ice, `>`, `<`, `^` and `v` conveyors, `R`, `G` and
+    /// `B` doors of their color.
+    pub(super) fn layout(rows: &[&str]) -> PuzzleLayout {
+        let height = rows.len() as i32;
+        let width = rows[0].len() as i32;
+        let mut layout = PuzzleLayout {
+            iid: "test".to_string(),
+            identifier: "Test".to_string(),
+            width,
+            height,
+            cells: vec![Cell::Empty; (width * height) as usize],
+            plates: Vec::new(),
+            finishes: Vec::new(),
+            start: GridCoords::default(),
+            crates: Vec::new(),
+            teleporters: Vec::new(),
+            keys: Vec::new(),
+            gates: Vec::new(),
+            laser_schedules: Vec::new(),
+            emitters: Vec::new(),
+            mirrors: Vec::new(),
+            colors: vec![ColorControl::Blue, ColorControl::Red],
+            par: None,
+        };
+        for (row, line) in rows.iter().enumerate() {
+            for (x, tile) in line.chars().enumerate() {
+                let coords = GridCoords {
+                    x: x as i32,
+                    y: height - row as i32 - 1,
+                };
+                let cell = match tile {
+                    '#' => Cell::Wall,
+                    'R' => Cell::Door(LaserType::Red),
+                    'G' => Cell::Door(LaserType::Green),
+                    'B' => Cell::Door(LaserType::Blue),
+                    'I' => Cell::Ice,
+                    '>' => Cell::Conveyor(Right),
+                    '<' => Cell::Conveyor(Left),
+                    '^' => Cell::Conveyor(Up),
+                    'v' => Cell::Conveyor(Down),
+                    _ => Cell::Floor,
+                };
+                match tile {
+                    '@' => layout.start = coords,
+                    'F' => layout.finishes.push(Finish {
+                        coords,
+                        ..Default::default()
+                    }),
+                    'P' => layout.plates.push(Plate {
+                        coords,
+                        ..Default::default()
+                    }),
+                    'C' => layout.crates.push(coords),
+                    _ => {}
+                }
+                layout.cells[(coords.y * width + coords.x) as usize] = cell;
+            }
+        }
+        layout
+    }
+
+    pub(super) fn state(rows: &[&str]) -> PuzzleState {
+        PuzzleState::new(layout(rows))
+    }
+
+    fn coords(x: i32, y: i32) -> GridCoords {
+        GridCoords { x, y }
+    }
+
+    #[test]
+    fn moves_onto_floor() {
+        let mut puzzle = state(&["@.."]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(1, 0)));
+        assert_eq!(puzzle.player, coords(1, 0));
+        assert!(!puzzle.is_moving());
+    }
+
+    #[test]
+    fn walls_and_level_edges_block() {
+        let mut puzzle = state(&["#@."]);
+        assert!(puzzle.step(PuzzleAction::Move(Left)).is_noop());
+        assert!(puzzle.step(PuzzleAction::Move(Up)).is_noop());
+        assert!(puzzle.step(PuzzleAction::Move(Down)).is_noop());
+        assert_eq!(puzzle.player, coords(1, 0));
+    }
+
+    #[test]
+    fn multi_move_continues_until_done() {
+        let mut layout = layout(&["@P...."]);
+        layout.plates[0].multi_movement = vec![(Right, 3)];
+        let mut puzzle = PuzzleState::new(layout);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.pressed_plates, vec![0]);
+        assert_eq!(puzzle.side_effects.get(Right), SideEffect::MultiMove(3));
+
+        puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(puzzle.player, coords(2, 0));
+        assert!(puzzle.is_moving());
+        // No new moves until the multi-move is over.
+        assert!(puzzle.step(PuzzleAction::Move(Left)).is_noop());
+        puzzle.step(PuzzleAction::Continue);
+        puzzle.step(PuzzleAction::Continue);
+        assert_eq!(puzzle.player, coords(4, 0));
+        assert!(!puzzle.is_moving());
+        assert!(puzzle.step(PuzzleAction::Continue).is_noop());
+    }
+
+    #[test]
+    fn multi_move_stops_at_a_wall() {
+        let mut layout = layout(&["@P..#"]);
+        layout.plates[0].multi_movement = vec![(Right, 5)];
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::Continue);
+        assert_eq!(puzzle.player, coords(3, 0));
+        puzzle.step(PuzzleAction::Continue);
+        assert_eq!(puzzle.player, coords(3, 0));
+        assert!(!puzzle.is_moving());
+    }
+
+    #[test]
+    fn plates_disable_movement() {
+        let mut layout = layout(&["@P."]);
+        layout.plates[0].forbids_movement = vec![Right];
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert_eq!(puzzle.player, coords(1, 0));
+        assert!(!puzzle.step(PuzzleAction::Move(Left)).is_noop());
+    }
+
+    #[test]
+    fn plates_open_lasers_of_their_color() {
+        let mut layout = layout(&["@PR."]);
+        layout.plates[0].opens_laser = Some(LaserType::Red);
+        let mut puzzle = PuzzleState::new(layout);
+        assert!(!puzzle.is_open_at(coords(2, 0)));
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_plate_active(0));
+        assert!(puzzle.is_open_at(coords(2, 0)));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert!(!outcome.died);
+    }
+
+    #[test]
+    fn timed_plates_count_turns_after_being_left() {
+        let mut layout = layout(&["@P..."]);
+        layout.plates[0].mode = PlateMode::Timed(2);
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        // Standing on the plate doesn't count.
+        puzzle.step(PuzzleAction::SwitchColor);
+        assert!(puzzle.is_plate_active(0));
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_plate_active(0));
+        // A color switch is a turn like a move.
+        let outcome = puzzle.step(PuzzleAction::SwitchColor);
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert!(!puzzle.is_plate_active(0));
+    }
+
+    #[test]
+    fn timed_plates_closing_under_the_player_kill() {
+        let mut layout = layout(&["@PG."]);
+        layout.plates[0].mode = PlateMode::Timed(2);
+        layout.plates[0].opens_laser = Some(LaserType::Green);
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(!puzzle.step(PuzzleAction::Move(Right)).died);
+        let outcome = puzzle.step(PuzzleAction::SwitchColor);
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn stepping_onto_a_door_as_its_timed_plate_runs_out_kills() {
+        let mut layout = layout(&["@PG."]);
+        layout.plates[0].mode = PlateMode::Timed(1);
+        layout.plates[0].opens_laser = Some(LaserType::Green);
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_open_at(coords(2, 0)));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(2, 0)));
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn hold_plates_let_go_once_left() {
+        let mut layout = layout(&["@PR."]);
+        layout.plates[0].mode = PlateMode::Hold;
+        layout.plates[0].opens_laser = Some(LaserType::Red);
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_open_at(coords(2, 0)));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn plate_side_effects_stay_after_release() {
+        let mut layout = layout(&[
+            "...", //
+            "@P.", //
+        ]);
+        layout.plates[0].mode = PlateMode::Toggle;
+        layout.plates[0].forbids_movement = vec![Up];
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::Move(Right));
+        let outcome = puzzle.step(PuzzleAction::Move(Left));
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert!(!puzzle.is_plate_active(0));
+        assert!(puzzle.step(PuzzleAction::Move(Up)).is_noop());
+    }
+
+    #[test]
+    fn dies_on_a_closed_laser() {
+        let mut puzzle = state(&["@R."]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert!(outcome.died);
+        assert_eq!(puzzle.status, PuzzleStatus::Dead);
+        // Nothing happens once the player is dead.
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert!(puzzle.step(PuzzleAction::SwitchColor).is_noop());
+    }
+
+    #[test]
+    fn lasers_of_the_players_color_are_open() {
+        let mut puzzle = state(&["@B."]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert!(!outcome.died);
+        // Switching away while standing in the laser is deadly.
+        let outcome = puzzle.step(PuzzleAction::SwitchColor);
+        assert!(outcome.color_switched);
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn reaching_a_finish_ends_the_level() {
+        let mut puzzle = state(&["@F."]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.finished, Some(0));
+        assert_eq!(puzzle.status, PuzzleStatus::Finished(0));
+        for direction in MovementDirection::all() {
+            assert_eq!(
+                puzzle.side_effects.get(direction),
+                SideEffect::DisabledMovement
+            );
+        }
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+    }
+
+    #[test]
+    fn switching_to_the_same_color_is_a_noop() {
+        let mut layout = layout(&["@."]);
+        layout.colors = vec![ColorControl::Red];
+        let mut puzzle = PuzzleState::new(layout);
+        assert!(puzzle.step(PuzzleAction::SwitchColor).is_noop());
+        assert_eq!(puzzle.color, ColorControl::Red);
+    }
+
+    #[test]
+    fn color_switches_cycle_through_the_level_colors() {
+        let mut puzzle = state(&["@."]);
+        assert_eq!(puzzle.color, ColorControl::Blue);
+        puzzle.step(PuzzleAction::SwitchColor);
+        assert_eq!(puzzle.color, ColorControl::Red);
+        puzzle.step(PuzzleAction::SwitchColor);
+        assert_eq!(puzzle.color, ColorControl::Blue);
+    }
+
+    #[test]
+    fn pushes_crates_along() {
+        let mut puzzle = state(&["@C.."]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(1, 0)));
+        assert_eq!(puzzle.crates, vec![coords(2, 0)]);
+    }
+
+    #[test]
+    fn crates_pushed_onto_plates_press_them() {
+        let mut layout = layout(&[
+            "....R", //
+            "@CP..", //
+        ]);
+        layout.plates[0].mode = PlateMode::Hold;
+        layout.plates[0].opens_laser = Some(LaserType::Red);
+        let mut puzzle = PuzzleState::new(layout);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.pressed_plates, vec![0]);
+        assert!(puzzle.is_open_at(coords(4, 1)));
+        // The player steps onto the plate as the crate leaves it, holding it down.
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert!(outcome.released_plates.is_empty());
+        assert!(puzzle.is_plate_active(0));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.released_plates, vec![0]);
+        assert_eq!(puzzle.crates, vec![coords(4, 0)]);
+        assert!(!puzzle.is_open_at(coords(4, 1)));
+    }
+
+    #[test]
+    fn plates_under_starting_crates_and_the_player_start_pressed() {
+        let mut layout = layout(&[
+            "R..", //
+            "@C.", //
+        ]);
+        for x in 0..2 {
+            layout.plates.push(Plate {
+                coords: coords(x, 0),
+                ..Default::default()
+            });
+        }
+        layout.plates[0].forbids_movement = vec![Right];
+        layout.plates[1].opens_laser = Some(LaserType::Red);
+        let mut puzzle = PuzzleState::new(layout);
+        assert!(puzzle.is_plate_active(0));
+        assert!(puzzle.is_plate_active(1));
+        // The player's plate stops them pushing the crate, the crate's opens the red laser.
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert!(puzzle.is_open_at(coords(0, 1)));
+        assert!(!puzzle.step(PuzzleAction::Move(Up)).died);
+    }
+
+    #[test]
+    fn crates_stop_at_walls_crates_and_closed_lasers() {
+        for rows in [["@C#"], ["@CC"], ["@CR"]] {
+            let mut puzzle = state(&rows);
+            let crates = puzzle.crates.clone();
+            assert!(
+                puzzle.step(PuzzleAction::Move(Right)).is_noop(),
+                "{:?}",
+                rows
+            );
+            assert_eq!(puzzle.player, coords(0, 0));
+            assert_eq!(puzzle.crates, crates);
+        }
+    }
+
+    #[test]
+    fn crates_stop_at_the_level_edge() {
+        let mut puzzle = state(&["@C"]);
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert_eq!(puzzle.crates, vec![coords(1, 0)]);
+    }
+
+    fn pads(layout: &mut PuzzleLayout, a: GridCoords, b: GridCoords, color: Option<ColorControl>) {
+        for (coords, partner) in [(a, b), (b, a)] {
+            layout.teleporters.push(Teleporter {
+                coords,
+                partner: Some(partner),
+                color,
+            });
+        }
+    }
+
+    #[test]
+    fn teleporters_send_the_player_to_their_partner() {
+        let mut layout = layout(&["@....."]);
+        pads(&mut layout, coords(1, 0), coords(4, 0), None);
+        let mut puzzle = PuzzleState::new(layout);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(1, 0)));
+        assert_eq!(outcome.teleported_to, Some(coords(4, 0)));
+        assert_eq!(puzzle.player, coords(4, 0));
+        // Arriving on the partner doesn't send the player back.
+        puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(puzzle.player, coords(5, 0));
+    }
+
+    #[test]
+    fn colored_teleporters_only_work_for_their_color() {
+        let mut layout = layout(&["@....."]);
+        pads(
+            &mut layout,
+            coords(1, 0),
+            coords(4, 0),
+            Some(ColorControl::Red),
+        );
+        let mut puzzle = PuzzleState::new(layout);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.teleported_to, None);
+        assert_eq!(puzzle.player, coords(1, 0));
+        // Switching color on the pad doesn't teleport, walking onto it does.
+        let outcome = puzzle.step(PuzzleAction::SwitchColor);
+        assert_eq!(outcome.teleported_to, None);
+        puzzle.step(PuzzleAction::Move(Left));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.teleported_to, Some(coords(4, 0)));
+    }
+
+    #[test]
+    fn teleporters_blocked_by_a_crate_do_nothing() {
+        let mut layout = layout(&["@...C."]);
+        pads(&mut layout, coords(1, 0), coords(4, 0), None);
+        let mut puzzle = PuzzleState::new(layout);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.teleported_to, None);
+        assert_eq!(puzzle.player, coords(1, 0));
+    }
+
+    /// Plays out the current multi-move, returning the tiles passed.
+    fn slide(puzzle: &mut PuzzleState) -> Vec<GridCoords> {
+        let mut tiles = Vec::new();
+        while puzzle.is_moving() {
+            let outcome = puzzle.step(PuzzleAction::Continue);
+            tiles.extend(outcome.moved_to);
+        }
+        tiles
+    }
+
+    #[test]
+    fn slides_over_ice_until_the_next_floor() {
+        let mut puzzle = state(&["@III.."]);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_moving());
+        assert_eq!(
+            slide(&mut puzzle),
+            vec![coords(2, 0), coords(3, 0), coords(4, 0)]
+        );
+        assert_eq!(puzzle.player, coords(4, 0));
+    }
+
+    #[test]
+    fn ice_slides_stop_at_walls() {
+        let mut puzzle = state(&["@II#"]);
+        puzzle.step(PuzzleAction::Move(Right));
+        slide(&mut puzzle);
+        assert_eq!(puzzle.player, coords(2, 0));
+        assert!(!puzzle.is_moving());
+        // Standing on ice, the player can walk off again.
+        puzzle.step(PuzzleAction::Move(Left));
+        slide(&mut puzzle);
+        assert_eq!(puzzle.player, coords(0, 0));
+    }
+
+    #[test]
+    fn ice_slides_into_closed_lasers_kill() {
+        let mut puzzle = state(&["@IR."]);
+        puzzle.step(PuzzleAction::Move(Right));
+        slide(&mut puzzle);
+        assert_eq!(puzzle.player, coords(2, 0));
+        assert_eq!(puzzle.status, PuzzleStatus::Dead);
+    }
+
+    #[test]
+    fn switching_color_while_sliding_opens_lasers_ahead() {
+        let mut puzzle = state(&["@IR."]);
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::SwitchColor);
+        slide(&mut puzzle);
+        assert_eq!(puzzle.player, coords(2, 0));
+        assert_eq!(puzzle.status, PuzzleStatus::Playing);
+    }
+
+    #[test]
+    fn conveyors_chain_until_one_is_blocked() {
+        let mut puzzle = state(&["@>>#"]);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(1, 0)));
+        assert!(puzzle.is_moving());
+        assert_eq!(slide(&mut puzzle), vec![coords(2, 0)]);
+        assert!(!puzzle.is_moving());
+    }
+
+    #[test]
+    fn conveyor_loops_carry_the_player_around() {
+        let mut puzzle = state(&[
+            "@>v", //
+            ".^<", //
+        ]);
+        puzzle.step(PuzzleAction::Move(Right));
+        let start = puzzle.clone();
+        let mut tiles = Vec::new();
+        for _ in 0..4 {
+            tiles.extend(puzzle.step(PuzzleAction::Continue).moved_to);
+        }
+        assert_eq!(
+            tiles,
+            vec![coords(2, 1), coords(2, 0), coords(1, 0), coords(1, 1)]
+        );
+        // Every lap plays out the same way.
+        assert_eq!(puzzle, start);
+    }
+
+    #[test]
+    fn one_way_gates_only_let_in_along_their_direction() {
+        let mut layout = layout(&["@..."]);
+        layout.cells[1] = Cell::OneWay(Right);
+        let mut puzzle = PuzzleState::new(layout);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(puzzle.player, coords(1, 0));
+        // Leaving works in any direction.
+        puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(puzzle.player, coords(2, 0));
+        assert!(puzzle.step(PuzzleAction::Move(Left)).is_noop());
+        // Crates can't be pushed in against it either.
+        puzzle.player = coords(3, 0);
+        puzzle.crates = vec![coords(2, 0)];
+        assert!(puzzle.step(PuzzleAction::Move(Left)).is_noop());
+        assert_eq!(puzzle.crates, vec![coords(2, 0)]);
+    }
+
+    /// A key at x = 1 and a lock at x = 3 along a corridor.
+    fn key_and_lock(key: ColorControl, lock: ColorControl) -> PuzzleState {
+        let mut layout = layout(&["@...."]);
+        layout.keys.push(Key {
+            coords: coords(1, 0),
+            color: key,
+        });
+        layout.cells[3] = Cell::Lock(lock);
+        PuzzleState::new(layout)
+    }
+
+    #[test]
+    fn keys_open_locks_of_their_color() {
+        let mut puzzle = key_and_lock(ColorControl::Red, ColorControl::Red);
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.picked_up_key, Some(0));
+        assert_eq!(puzzle.inventory, vec![ColorControl::Red]);
+        puzzle.step(PuzzleAction::Move(Right));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.unlocked, Some(coords(3, 0)));
+        assert_eq!(puzzle.player, coords(3, 0));
+        // The key is used up, the lock stays open.
+        assert!(puzzle.inventory.is_empty());
+        assert_eq!(puzzle.closed_lock_at(coords(3, 0)), None);
+        puzzle.step(PuzzleAction::Move(Left));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(3, 0)));
+        assert_eq!(outcome.unlocked, None);
+    }
+
+    #[test]
+    fn keys_are_picked_up_once() {
+        let mut puzzle = key_and_lock(ColorControl::Red, ColorControl::Red);
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::Move(Left));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.picked_up_key, None);
+        assert_eq!(puzzle.inventory, vec![ColorControl::Red]);
+    }
+
+    #[test]
+    fn locks_block_without_a_key_of_their_color() {
+        let mut puzzle = key_and_lock(ColorControl::Blue, ColorControl::Red);
+        puzzle.step(PuzzleAction::Move(Right));
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert_eq!(puzzle.inventory, vec![ColorControl::Blue]);
+        assert_eq!(puzzle.closed_lock_at(coords(3, 0)), Some(ColorControl::Red));
+    }
+
+    #[test]
+    fn crates_stop_at_closed_locks() {
+        let mut puzzle = key_and_lock(ColorControl::Red, ColorControl::Red);
+        puzzle.crates = vec![coords(2, 0)];
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.step(PuzzleAction::Move(Right)).is_noop());
+        assert_eq!(puzzle.crates, vec![coords(2, 0)]);
+    }
+
+    /// Two plates wired into one gate that powers the green laser at x = 4, which the
+    /// player's own colors never open.
+    fn circuit(logic: GateLogic) -> PuzzleState {
+        let mut layout = layout(&["@PP.G."]);
+        for plate in layout.plates.iter_mut() {
+            plate.wires = vec![Wire::Gate(0)];
+        }
+        layout.gates.push(Gate {
+            coords: coords(3, 0),
+            logic,
+            outputs: vec![Wire::Laser(coords(4, 0))],
+        });
+        PuzzleState::new(layout)
+    }
+
+    /// Whether the gate and its laser are on before and after each plate is stepped on.
+    fn circuit_signals(logic: GateLogic) -> Vec<(bool, bool)> {
+        let mut puzzle = circuit(logic);
+        let mut signals = vec![(puzzle.is_gate_on(0), puzzle.is_open_at(coords(4, 0)))];
+        for _ in 0..2 {
+            puzzle.step(PuzzleAction::Move(Right));
+            signals.push((puzzle.is_gate_on(0), puzzle.is_open_at(coords(4, 0))));
+        }
+        signals
+    }
+
+    #[test]
+    fn and_gates_need_every_input() {
+        assert_eq!(
+            circuit_signals(GateLogic::And),
+            vec![(false, false), (false, false), (true, true)]
+        );
+    }
+
+    #[test]
+    fn or_gates_need_any_input() {
+        assert_eq!(
+            circuit_signals(GateLogic::Or),
+            vec![(false, false), (true, true), (true, true)]
+        );
+    }
+
+    #[test]
+    fn not_gates_are_on_without_inputs() {
+        assert_eq!(
+            circuit_signals(GateLogic::Not),
+            vec![(true, true), (false, false), (false, false)]
+        );
+    }
+
+    #[test]
+    fn gates_feed_into_gates() {
+        let mut layout = layout(&["@P.G."]);
+        layout.plates[0].wires = vec![Wire::Gate(0)];
+        layout.gates = vec![
+            Gate {
+                logic: GateLogic::Not,
+                outputs: vec![Wire::Gate(1)],
+                ..Default::default()
+            },
+            Gate {
+                logic: GateLogic::Not,
+                outputs: vec![Wire::Laser(coords(3, 0))],
+                ..Default::default()
+            },
+        ];
+        let mut puzzle = PuzzleState::new(layout);
+        assert!(!puzzle.is_open_at(coords(3, 0)));
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_open_at(coords(3, 0)));
+    }
+
+    #[test]
+    fn gates_wired_into_a_loop_read_their_own_output_as_off() {
+        let mut layout = layout(&["@G."]);
+        layout.gates.push(Gate {
+            logic: GateLogic::Not,
+            outputs: vec![Wire::Gate(0), Wire::Laser(coords(1, 0))],
+            ..Default::default()
+        });
+        let mut puzzle = PuzzleState::new(layout);
+        assert!(puzzle.is_gate_on(0));
+        assert!(!puzzle.step(PuzzleAction::Move(Right)).died);
+    }
+
+    /// A green door at x = 1, which only its schedule opens.
+    fn timed_laser(open_turns: u32, closed_turns: u32, offset: u32) -> PuzzleState {
+        let mut layout = layout(&["@G."]);
+        layout.laser_schedules.push(LaserSchedule {
+            coords: coords(1, 0),
+            open_turns,
+            closed_turns,
+            offset,
+        });
+        PuzzleState::new(layout)
+    }
+
+    #[test]
+    fn walking_onto_a_timed_laser_on_its_last_open_turn_kills() {
+        let mut puzzle = timed_laser(1, 1, 0);
+        assert!(puzzle.is_open_at(coords(1, 0)));
+        let outcome = puzzle.step(PuzzleAction::Move(Right));
+        assert_eq!(outcome.moved_to, Some(coords(1, 0)));
+        assert!(outcome.died);
+        assert_eq!(puzzle.status, PuzzleStatus::Dead);
+    }
+
+    #[test]
+    fn timed_lasers_closing_under_a_player_standing_still_kill() {
+        let mut puzzle = timed_laser(2, 1, 0);
+        puzzle.step(PuzzleAction::Move(Right));
+        assert!(puzzle.is_open_at(coords(1, 0)));
+        let outcome = puzzle.step(PuzzleAction::SwitchColor);
+        assert!(outcome.died);
+    }
+
+    #[test]
+    fn turn_offsets_shift_the_cycle() {
+        let mut puzzle = timed_laser(2, 2, 2);
+        assert!(!puzzle.is_open_at(coords(1, 0)));
+        assert!(puzzle.step(PuzzleAction::Move(Right)).died);
+        let mut puzzle = timed_laser(2, 2, 2);
+        puzzle.step(PuzzleAction::SwitchColor);
+        puzzle.step(PuzzleAction::SwitchColor);
+        assert!(puzzle.is_open_at(coords(1, 0)));
+        assert!(!puzzle.step(PuzzleAction::Move(Right)).died);
+    }
+
+    #[test]
+    fn the_clock_wraps_around_the_common_period() {
+        let mut layout = layout(&["@..GG"]);
+        for (x, open_turns) in [(3, 1), (4, 2)] {
+            layout.laser_schedules.push(LaserSchedule {
+                coords: coords(x, 0),
+                open_turns,
+                closed_turns: 1,
+                offset: 0,
+            });
+        }
+        assert_eq!(layout.clock_period(), Some(6));
+        let mut puzzle = PuzzleState::new(layout);
+        let mut clocks = Vec::new();
+        for _ in 0..7 {
+            puzzle.step(PuzzleAction::SwitchColor);
+            clocks.push(puzzle.clock);
+        }
+        assert_eq!(clocks, vec![1, 2, 3, 4, 5, 0, 1]);
+    }
+
+    #[test]
+    fn clock_periods_past_the_limit_are_rejected() {
+        let mut layout = layout(&["@GG"]);
+        for (x, open_turns) in [(1, 19), (2, 20)] {
+            layout.laser_schedules.push(LaserSchedule {
+                coords: coords(x, 0),
+                open_turns,
+                closed_turns: 1,
+                offset: 0,
+            });
+        }
+        assert_eq!(layout.clock_period(), None);
+        layout.laser_schedules[1].open_turns = 9;
+        assert_eq!(layout.clock_period(), Some(20));
+        // Cycles too long to multiply out at all are rejected the same way.
+        layout.laser_schedules[1].open_turns = u32::MAX - 1;
+        assert_eq!(layout.clock_period(), None);
+    }
+
+    /// A layout with a red emitter shooting right from `emitter`, and `mirrors`.
+    fn beam_layout(rows: &[&str], emitter: GridCoords, mirrors: &[Mirror]) -> PuzzleLayout {
+        let mut layout = layout(rows);
+        layout.emitters.push(Emitter {
+            coords: emitter,
+            direction: Right,
+            laser_type: LaserType::Red,
+        });
+        layout.mirrors = mirrors.to_vec();
+        layout
+    }
+
+    fn beam_tiles(puzzle: &PuzzleState) -> Vec<GridCoords> {
+        puzzle.beam_cells().iter().map(|beam| beam.coords).collect()
+    }
+
+    fn mirror(x: i32, y: i32, orientation: MirrorOrientation) -> Mirror {
+        Mirror {
+            coords: coords(x, y),
+            orientation,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn beams_run_until_a_wall() {
+        let rows = [
+            "@....", //
+            "...#.", //
+        ];
+        let puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 0), &[]));
+        assert_eq!(beam_tiles(&puzzle), vec![coords(1, 0), coords(2, 0)]);
+        assert!(!puzzle.is_open_at(coords(2, 0)));
+        // Doors are checked on their own, beams aside.
+        assert!(puzzle.is_door_open_at(coords(2, 0)));
+    }
+
+    #[test]
+    fn mirrors_deflect_beams() {
+        let rows = [
+            "@..", //
+            "...", //
+            "...", //
+        ];
+        let slash = [mirror(2, 0, MirrorOrientation::Slash)];
+        let puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 0), &slash));
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 0), coords(2, 0), coords(2, 1), coords(2, 2)]
+        );
+        assert_eq!(puzzle.beam_cells()[1].direction, Up);
+        let backslash = [mirror(2, 2, MirrorOrientation::Backslash)];
+        let puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 2), &backslash));
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 2), coords(2, 2), coords(2, 1), coords(2, 0)]
+        );
+        assert_eq!(puzzle.beam_cells()[1].direction, Down);
+    }
+
+    #[test]
+    fn crates_and_closed_locks_stop_beams() {
+        let rows = [
+            "@....", //
+            "..C..", //
+        ];
+        let puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 0), &[]));
+        assert_eq!(beam_tiles(&puzzle), vec![coords(1, 0)]);
+        let rows = [
+            "...@.", //
+            ".....", //
+        ];
+        let mut layout = beam_layout(&rows, coords(0, 0), &[]);
+        layout.cells[3] = Cell::Lock(ColorControl::Red);
+        let mut puzzle = PuzzleState::new(layout);
+        assert_eq!(beam_tiles(&puzzle), vec![coords(1, 0), coords(2, 0)]);
+        // Opening the lock lets the beam through.
+        puzzle.inventory.push(ColorControl::Red);
+        puzzle.step(PuzzleAction::SwitchColor);
+        let outcome = puzzle.step(PuzzleAction::Move(Down));
+        assert_eq!(outcome.unlocked, Some(coords(3, 0)));
+        assert!(!outcome.died);
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 0), coords(2, 0), coords(3, 0), coords(4, 0)]
+        );
+    }
+
+    #[test]
+    fn pushing_a_crate_out_of_a_beam_lets_it_through() {
+        let rows = [
+            "....", //
+            "..C.", //
+            "..@.", //
+        ];
+        let mut puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 1), &[]));
+        assert_eq!(beam_tiles(&puzzle), vec![coords(1, 1)]);
+        puzzle.step(PuzzleAction::SwitchColor);
+        let outcome = puzzle.step(PuzzleAction::Move(Up));
+        assert!(!outcome.died);
+        assert_eq!(puzzle.crates, vec![coords(2, 2)]);
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 1), coords(2, 1), coords(3, 1)]
+        );
+    }
+
+    #[test]
+    fn rotating_a_mirror_turns_the_beam() {
+        let rows = [
+            "...", //
+            "..@", //
+            "...", //
+        ];
+        let mut rotatable = mirror(2, 0, MirrorOrientation::Slash);
+        rotatable.rotatable = true;
+        let mut puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 0), &[rotatable]));
+        assert_eq!(puzzle.beam_cells()[1].direction, Up);
+        let outcome = puzzle.step(PuzzleAction::Move(Down));
+        assert_eq!(outcome.rotated_mirror, Some(0));
+        assert_eq!(puzzle.player, coords(2, 1));
+        assert_eq!(puzzle.mirrors[0].orientation, MirrorOrientation::Backslash);
+        assert_eq!(beam_tiles(&puzzle), vec![coords(1, 0), coords(2, 0)]);
+        assert_eq!(puzzle.beam_cells()[1].direction, Down);
+    }
+
+    #[test]
+    fn pushed_mirrors_take_the_beam_along_but_dont_press_plates() {
+        let rows = [
+            "..@.", //
+            "....", //
+            "....", //
+            "..P.", //
+        ];
+        let mut pushable = mirror(2, 2, MirrorOrientation::Slash);
+        pushable.pushable = true;
+        let mut puzzle = PuzzleState::new(beam_layout(&rows, coords(0, 2), &[pushable]));
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 2), coords(2, 2), coords(2, 3)]
+        );
+        puzzle.step(PuzzleAction::SwitchColor);
+        puzzle.step(PuzzleAction::Move(Down));
+        assert_eq!(puzzle.mirrors[0].coords, coords(2, 1));
+        assert_eq!(
+            beam_tiles(&puzzle),
+            vec![coords(1, 2), coords(2, 2), coords(3, 2)]
+        );
+        let outcome = puzzle.step(PuzzleAction::Move(Down));
+        assert_eq!(puzzle.mirrors[0].coords, coords(2, 0));
+        assert!(outcome.pressed_plates.is_empty());
+        assert!(!puzzle.is_plate_active(0));
+    }
+}
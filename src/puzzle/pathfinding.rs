@@ -0,0 +1,121 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy_ecs_ldtk::GridCoords;
+
+use crate::actions::MovementDirection;
+
+use super::{PuzzleAction, PuzzleState, PuzzleStatus, StepOutcome};
+
+/// Finds the fewest moves that bring the player to rest on `target`, without switching color.
+///
+/// Moves are simulated with [`PuzzleState::step`], so closed lasers, teleporters, ice and
+/// conveyors are all taken into account. Only moves that leave the level as it was are tried:
+/// pushing crates or mirrors, turning mirrors, pressing plates and picking up keys or using
+/// them are left to the player, and targets that need them have no path.
+/// Only the inputs are returned, the `Continue`s of multi-moves happen on their own.
+pub fn find_path(start: &PuzzleState, target: GridCoords) -> Option<Vec<MovementDirection>> {
+    let mut parents: HashMap<PuzzleState, Option<(PuzzleState, PuzzleAction)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(start.clone(), None);
+    queue.push_back(start.clone());
+
+    while let Some(state) = queue.pop_front() {
+        if state.player == target && !state.is_moving() && state.status != PuzzleStatus::Dead {
+            return Some(backtrack_moves(&parents, &state));
+        }
+        if state.status != PuzzleStatus::Playing {
+            continue;
+        }
+        let actions = if state.is_moving() {
+            vec![PuzzleAction::Continue]
+        } else {
+            MovementDirection::all()
+                .into_iter()
+                .map(PuzzleAction::Move)
+                .collect()
+        };
+        for action in actions {
+            let mut next = state.clone();
+            let outcome = next.step(action);
+            if next.status == PuzzleStatus::Dead
+                || changes_level(&state, &next, &outcome)
+                || parents.contains_key(&next)
+            {
+                continue;
+            }
+            parents.insert(next.clone(), Some((state.clone(), action)));
+            // Continues are free, keep them ahead of the states one more input away.
+            if action == PuzzleAction::Continue {
+                queue.push_front(next);
+            } else {
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Plates running out or being left still count as walking, nothing was done to them. So does
+/// crossing a plate that was already active, like a latched one.
+fn changes_level(before: &PuzzleState, after: &PuzzleState, outcome: &StepOutcome) -> bool {
+    let switched_plate = outcome
+        .pressed_plates
+        .iter()
+        .any(|&index| before.is_plate_active(index) != after.is_plate_active(index));
+    switched_plate
+        || outcome.picked_up_key.is_some()
+        || outcome.unlocked.is_some()
+        || outcome.rotated_mirror.is_some()
+        || before.crates != after.crates
+        || before.mirrors != after.mirrors
+}
+
+fn backtrack_moves(
+    parents: &HashMap<PuzzleState, Option<(PuzzleState, PuzzleAction)>>,
+    end: &PuzzleState,
+) -> Vec<MovementDirection> {
+    let mut moves = Vec::new();
+    let mut current = end;
+    while let Some(Some((parent, action))) = parents.get(current) {
+        if let PuzzleAction::Move(direction) = action {
+            moves.push(*direction);
+        }
+        current = parent;
+    }
+    moves.reverse();
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::tests::{layout, state};
+    use MovementDirection::*;
+
+    #[test]
+    fn walks_around_crates_instead_of_pushing_them() {
+        let mut layout = layout(&[
+            "@...", //
+            "....", //
+        ]);
+        layout.crates.push(GridCoords { x: 1, y: 1 });
+        let start = PuzzleState::new(layout);
+        let path = find_path(&start, GridCoords { x: 2, y: 1 });
+        assert_eq!(path, Some(vec![Down, Right, Right, Up]));
+    }
+
+    #[test]
+    fn refuses_targets_that_need_a_plate_pressed() {
+        let start = state(&["@P."]);
+        assert_eq!(find_path(&start, GridCoords { x: 1, y: 0 }), None);
+        assert_eq!(find_path(&start, GridCoords { x: 2, y: 0 }), None);
+    }
+
+    #[test]
+    fn crosses_plates_that_are_already_latched() {
+        let mut start = state(&["@P."]);
+        start.active_plates[0] = true;
+        let path = find_path(&start, GridCoords { x: 2, y: 0 });
+        assert_eq!(path, Some(vec![Right, Right]));
+    }
+}
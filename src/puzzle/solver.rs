@@ -0,0 +1,217 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::actions::MovementDirection;
+
+use super::{PuzzleAction, PuzzleState, PuzzleStatus};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// Every step to replay with [`PuzzleState::step`], including the
+    /// [`PuzzleAction::Continue`]s of multi-moves.
+    pub actions: Vec<PuzzleAction>,
+    /// Index of the reached finish in [`super::PuzzleLayout::finishes`].
+    pub finish: usize,
+}
+
+impl Solution {
+    /// Number of actions the player has to input, i.e. without the `Continue`s.
+    pub fn input_count(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|action| **action != PuzzleAction::Continue)
+            .count()
+    }
+}
+
+/// Actions worth trying from `state`, paired with how many player inputs they cost.
+pub fn candidate_actions(state: &PuzzleState) -> Vec<(PuzzleAction, usize)> {
+    if state.is_moving() {
+        return vec![(PuzzleAction::Continue, 0), (PuzzleAction::SwitchColor, 1)];
+    }
+    MovementDirection::all()
+        .into_iter()
+        .map(|direction| (PuzzleAction::Move(direction), 1))
+        .chain([(PuzzleAction::SwitchColor, 1)])
+        .collect()
+}
+
+/// Finds a solution with the fewest player inputs that reaches any finish.
+///
+/// Returns `None` when every reachable state has been explored without finishing,
+/// which proves the level unsolvable from `start`.
+pub fn solve(start: &PuzzleState) -> Option<Solution> {
+    let mut parents: HashMap<PuzzleState, Option<(PuzzleState, PuzzleAction)>> = HashMap::new();
+    let mut costs: HashMap<PuzzleState, usize> = HashMap::new();
+    let mut queue = VecDeque::new();
+    costs.insert(start.clone(), 0);
+    parents.insert(start.clone(), None);
+    queue.push_back((start.clone(), 0));
+
+    while let Some((state, cost)) = queue.pop_front() {
+        if costs.get(&state).is_some_and(|best| *best < cost) {
+            continue;
+        }
+        if let PuzzleStatus::Finished(finish) = state.status {
+            return Some(Solution {
+                actions: backtrack(&parents, &state),
+                finish,
+            });
+        }
+        if state.status != PuzzleStatus::Playing {
+            continue;
+        }
+        for (action, action_cost) in candidate_actions(&state) {
+            let mut next = state.clone();
+            next.step(action);
+            if next == state {
+                continue;
+            }
+            let next_cost = cost + action_cost;
+            if costs.get(&next).is_some_and(|best| *best <= next_cost) {
+                continue;
+            }
+            costs.insert(next.clone(), next_cost);
+            parents.insert(next.clone(), Some((state.clone(), action)));
+            if action_cost == 0 {
+                queue.push_front((next, next_cost));
+            } else {
+                queue.push_back((next, next_cost));
+            }
+        }
+    }
+    None
+}
+
+/// Explores every state reachable from `start` and returns the indices of the finishes it can reach.
+pub fn reachable_finishes(start: &PuzzleState) -> BTreeSet<usize> {
+    let mut finishes = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(start.clone());
+    queue.push_back(start.clone());
+
+    while let Some(state) = queue.pop_front() {
+        match state.status {
+            PuzzleStatus::Finished(finish) => {
+                finishes.insert(finish);
+                continue;
+            }
+            PuzzleStatus::Dead => continue,
+            PuzzleStatus::Playing => {}
+        }
+        for (action, _) in candidate_actions(&state) {
+            let mut next = state.clone();
+            next.step(action);
+            if !visited.contains(&next) {
+                visited.insert(next.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    finishes
+}
+
+fn backtrack(
+    parents: &HashMap<PuzzleState, Option<(PuzzleState, PuzzleAction)>>,
+    end: &PuzzleState,
+) -> Vec<PuzzleAction> {
+    let mut actions = Vec::new();
+    let mut current = end;
+    while let Some(Some((parent, action))) = parents.get(current) {
+        actions.push(*action);
+        current = parent;
+    }
+    actions.reverse();
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actions::MovementDirection::*,
+        player::color_control::ColorControl,
+        puzzle::tests::{layout, state},
+    };
+
+    use super::*;
+
+    fn replay(start: &PuzzleState, solution: &Solution) -> PuzzleState {
+        let mut state = start.clone();
+        for action in solution.actions.iter() {
+            state.step(*action);
+        }
+        state
+    }
+
+    #[test]
+    fn finds_the_shortest_walk() {
+        let start = state(&[
+            "@.#F", //
+            ".#..", //
+            "....", //
+        ]);
+        let solution = solve(&start).unwrap();
+        assert_eq!(solution.input_count(), 7);
+        assert_eq!(solution.finish, 0);
+        assert_eq!(replay(&start, &solution).status, PuzzleStatus::Finished(0));
+    }
+
+    #[test]
+    fn color_switches_cost_one_input() {
+        let start = state(&["@RF"]);
+        let solution = solve(&start).unwrap();
+        assert_eq!(
+            solution.actions,
+            vec![
+                PuzzleAction::SwitchColor,
+                PuzzleAction::Move(Right),
+                PuzzleAction::Move(Right)
+            ]
+        );
+        assert_eq!(solution.input_count(), 3);
+    }
+
+    #[test]
+    fn switches_color_in_the_middle_of_a_multi_move() {
+        let mut layout = layout(&["@P.B.R.F"]);
+        layout.plates[0].multi_movement = vec![(Right, 6)];
+        let start = PuzzleState::new(layout);
+        let solution = solve(&start).unwrap();
+        // Continues are free, only the two moves and the switch count.
+        assert_eq!(solution.input_count(), 3);
+        assert_eq!(
+            solution.actions,
+            vec![
+                PuzzleAction::Move(Right),
+                PuzzleAction::Move(Right),
+                PuzzleAction::Continue,
+                PuzzleAction::Continue,
+                PuzzleAction::SwitchColor,
+                PuzzleAction::Continue,
+                PuzzleAction::Continue,
+                PuzzleAction::Continue,
+            ]
+        );
+        assert_eq!(replay(&start, &solution).status, PuzzleStatus::Finished(0));
+    }
+
+    #[test]
+    fn walled_off_finish_is_unsolvable() {
+        let start = state(&["@.#F"]);
+        assert_eq!(solve(&start), None);
+        assert!(reachable_finishes(&start).is_empty());
+    }
+
+    #[test]
+    fn laser_without_a_matching_color_is_unsolvable() {
+        let mut layout = layout(&["@GF"]);
+        layout.colors = vec![ColorControl::Blue, ColorControl::Red];
+        assert_eq!(solve(&PuzzleState::new(layout)), None);
+    }
+
+    #[test]
+    fn reachable_finishes_lists_every_reachable_finish() {
+        let start = state(&["F.@.F#F"]);
+        assert_eq!(reachable_finishes(&start), BTreeSet::from([0, 1]));
+    }
+}
@@ -0,0 +1,519 @@
+//! Checks that catch broken levels before they are played.
+
+use std::{collections::HashSet, fmt::Display};
+
+use bevy_ecs_ldtk::ldtk::{EntityInstance, Level};
+
+use super::{
+    ldtk::{
+        color_from_enum, entity_ref_field, entity_refs_field, enum_field, enums_field, find_layer,
+        ints_field, laser_type_from_enum, string_field,
+    },
+    solver::{reachable_finishes, solve},
+    Cell, PuzzleError, PuzzleLayout, PuzzleState, MAX_CLOCK_PERIOD,
+};
+
+/// A problem found in a level. Coordinates are LDtk grid coordinates, as shown in the editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelIssue {
+    MissingLayer(&'static str),
+    MissingPlayer,
+    MissingFinish,
+    InvalidIntGridValue {
+        x: i32,
+        y: i32,
+        value: i32,
+    },
+    InvalidDoor {
+        entity: String,
+        x: i32,
+        y: i32,
+        value: String,
+    },
+    InvalidMultiMoveValue {
+        x: i32,
+        y: i32,
+        value: i32,
+    },
+    MultiMoveLengthMismatch {
+        x: i32,
+        y: i32,
+        directions: usize,
+        values: usize,
+    },
+    UnresolvedNextLevel {
+        x: i32,
+        y: i32,
+        iid: String,
+    },
+    UnreachableFinish {
+        x: i32,
+        y: i32,
+    },
+    UnlinkedTeleporter {
+        x: i32,
+        y: i32,
+    },
+    InvalidKeyColor {
+        x: i32,
+        y: i32,
+    },
+    InvalidWire {
+        entity: String,
+        x: i32,
+        y: i32,
+    },
+    ParBelowOptimal {
+        par: u32,
+        optimal: usize,
+    },
+    ClockPeriodTooLong,
+}
+
+impl Display for LevelIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelIssue::MissingLayer(identifier) => write!(f, "missing layer {}", identifier),
+            LevelIssue::MissingPlayer => write!(f, "missing Player entity"),
+            LevelIssue::MissingFinish => write!(f, "missing Finish entity"),
+            LevelIssue::InvalidIntGridValue { x, y, value } => {
+                write!(f, "invalid IntGrid value {} at [{}, {}]", value, x, y)
+            }
+            LevelIssue::InvalidDoor {
+                entity,
+                x,
+                y,
+                value,
+            } => write!(
+                f,
+                "{} at [{}, {}] has unknown door color {:?}",
+                entity, x, y, value
+            ),
+            LevelIssue::InvalidMultiMoveValue { x, y, value } => write!(
+                f,
+                "Panel at [{}, {}] has Multi_Move_Values entry {}, it must be at least 1",
+                x, y, value
+            ),
+            LevelIssue::MultiMoveLengthMismatch {
+                x,
+                y,
+                directions,
+                values,
+            } => write!(
+                f,
+                "Panel at [{}, {}] has {} Wasd_Multi_Move entries but {} Multi_Move_Values",
+                x, y, directions, values
+            ),
+            LevelIssue::UnresolvedNextLevel { x, y, iid } => write!(
+                f,
+                "Finish at [{}, {}] points to unknown level {}",
+                x, y, iid
+            ),
+            LevelIssue::UnreachableFinish { x, y } => {
+                write!(f, "Finish at [{}, {}] can't be reached", x, y)
+            }
+            LevelIssue::UnlinkedTeleporter { x, y } => {
+                write!(f, "Teleporter at [{}, {}] has no Partner", x, y)
+            }
+            LevelIssue::InvalidKeyColor { x, y } => {
+                write!(f, "Key at [{}, {}] needs a Red, Green or Blue Color", x, y)
+            }
+            LevelIssue::InvalidWire { entity, x, y } => write!(
+                f,
+                "{} at [{}, {}] is wired to something that isn't a Laser or Gate",
+                entity, x, y
+            ),
+            LevelIssue::ParBelowOptimal { par, optimal } => write!(
+                f,
+                "Par is {} but the best solution takes {} inputs",
+                par, optimal
+            ),
+            LevelIssue::ClockPeriodTooLong => write!(
+                f,
+                "timed lasers take more than {} turns to line up again, shorten their cycles",
+                MAX_CLOCK_PERIOD
+            ),
+        }
+    }
+}
+
+/// What [`validate_level`] found out about a level.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LevelReport {
+    pub issues: Vec<LevelIssue>,
+    /// Inputs of the shortest solution, `None` when the level can't be built or solved.
+    pub optimal: Option<usize>,
+}
+
+/// Validates `level` against the IIDs of every level in the same project. Levels that can be
+/// built are also explored for unreachable finishes and solved once, whatever else is wrong.
+pub fn validate_level(level: &Level, level_iids: &HashSet<&str>) -> LevelReport {
+    let mut issues = Vec::new();
+
+    match find_layer(level, "IntGrid") {
+        Some(int_grid) => {
+            for (index, value) in int_grid.int_grid_csv.iter().enumerate() {
+                if Cell::from_int_grid_value(*value).is_none() {
+                    let index = index as i32;
+                    issues.push(LevelIssue::InvalidIntGridValue {
+                        x: index % int_grid.c_wid,
+                        y: index / int_grid.c_wid,
+                        value: *value,
+                    });
+                }
+            }
+        }
+        None => issues.push(LevelIssue::MissingLayer("IntGrid")),
+    }
+
+    let Some(entities) = find_layer(level, "Entities") else {
+        issues.push(LevelIssue::MissingLayer("Entities"));
+        return LevelReport {
+            issues,
+            optimal: None,
+        };
+    };
+    let entity_instances = &entities.entity_instances;
+    if !entity_instances
+        .iter()
+        .any(|entity_instance| entity_instance.identifier == "Player")
+    {
+        issues.push(LevelIssue::MissingPlayer);
+    }
+    let finishes: Vec<_> = entity_instances
+        .iter()
+        .filter(|entity_instance| entity_instance.identifier == "Finish")
+        .collect();
+    if finishes.is_empty() {
+        issues.push(LevelIssue::MissingFinish);
+    }
+    for entity_instance in entity_instances.iter() {
+        issues.extend(validate_entity(entity_instance, level_iids));
+    }
+    let wire_targets: HashSet<&str> = entity_instances
+        .iter()
+        .filter(|entity_instance| matches!(entity_instance.identifier.as_str(), "Laser" | "Gate"))
+        .map(|entity_instance| entity_instance.iid.as_str())
+        .collect();
+    for entity_instance in entity_instances.iter() {
+        let wires = match entity_instance.identifier.as_str() {
+            "Panel" => entity_refs_field(&entity_instance.field_instances, "Wires"),
+            "Gate" => entity_refs_field(&entity_instance.field_instances, "Outputs"),
+            _ => continue,
+        };
+        if wires.iter().any(|iid| !wire_targets.contains(iid.as_str())) {
+            issues.push(LevelIssue::InvalidWire {
+                entity: entity_instance.identifier.clone(),
+                x: entity_instance.grid.x,
+                y: entity_instance.grid.y,
+            });
+        }
+    }
+
+    let mut optimal = None;
+    match PuzzleLayout::from_level(level) {
+        Ok(layout) => {
+            let par = layout.par;
+            let start = PuzzleState::new(layout);
+            let reachable = reachable_finishes(&start);
+            for (index, finish) in finishes.iter().enumerate() {
+                if !reachable.contains(&index) {
+                    issues.push(LevelIssue::UnreachableFinish {
+                        x: finish.grid.x,
+                        y: finish.grid.y,
+                    });
+                }
+            }
+            // Nothing to solve for when no finish can be reached.
+            if !reachable.is_empty() {
+                optimal = solve(&start).map(|solution| solution.input_count());
+            }
+            if let (Some(par), Some(optimal)) = (par, optimal) {
+                if (par as usize) < optimal {
+                    issues.push(LevelIssue::ParBelowOptimal { par, optimal });
+                }
+            }
+        }
+        Err(PuzzleError::ClockPeriodTooLong) => issues.push(LevelIssue::ClockPeriodTooLong),
+        // Reported above from the layers themselves.
+        Err(_) => {}
+    }
+    LevelReport { issues, optimal }
+}
+
+fn validate_entity(
+    entity_instance: &EntityInstance,
+    level_iids: &HashSet<&str>,
+) -> Vec<LevelIssue> {
+    let mut issues = Vec::new();
+    let fields = &entity_instance.field_instances;
+    let (x, y) = (entity_instance.grid.x, entity_instance.grid.y);
+    let door_field = match entity_instance.identifier.as_str() {
+        "Panel" => Some("Door"),
+        "Laser" | "Emitter" => Some("Color"),
+        _ => None,
+    };
+    if let Some(door) = door_field.and_then(|door_field| enum_field(fields, door_field)) {
+        if laser_type_from_enum(&door).is_none() {
+            issues.push(LevelIssue::InvalidDoor {
+                entity: entity_instance.identifier.clone(),
+                x,
+                y,
+                value: door,
+            });
+        }
+    }
+    match entity_instance.identifier.as_str() {
+        "Panel" => {
+            let directions = enums_field(fields, "Wasd_Multi_Move").len();
+            let values = ints_field(fields, "Multi_Move_Values");
+            if directions != values.len() {
+                issues.push(LevelIssue::MultiMoveLengthMismatch {
+                    x,
+                    y,
+                    directions,
+                    values: values.len(),
+                });
+            }
+            for value in values.into_iter().filter(|value| *value < 1) {
+                issues.push(LevelIssue::InvalidMultiMoveValue { x, y, value });
+            }
+        }
+        "Teleporter" if entity_ref_field(fields, "Partner").is_none() => {
+            issues.push(LevelIssue::UnlinkedTeleporter { x, y });
+        }
+        "Key"
+            if enum_field(fields, "Color")
+                .and_then(|color| color_from_enum(&color))
+                .is_none() =>
+        {
+            issues.push(LevelIssue::InvalidKeyColor { x, y });
+        }
+        "Finish" => {
+            if let Some(iid) = string_field(fields, "NextLevel") {
+                if !level_iids.contains(iid.as_str()) {
+                    issues.push(LevelIssue::UnresolvedNextLevel { x, y, iid });
+                }
+            }
+        }
+        _ => {}
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue, LayerInstance, Type};
+
+    use super::*;
+
+    /// Builds a level from rows drawn top to bottom: `#` wall, `.` floor, and on floor `@` the
+    /// player, `F` a finish and `P` a panel.
+    fn level(rows: &[&str]) -> Level {
+        let mut int_grid = layer("IntGrid", Type::IntGrid, rows);
+        let mut entities = layer("Entities", Type::Entities, rows);
+        for (y, line) in rows.iter().enumerate() {
+            for (x, tile) in line.chars().enumerate() {
+                int_grid.int_grid_csv.push(if tile == '#' { 1 } else { 2 });
+                let identifier = match tile {
+                    '@' => "Player",
+                    'F' => "Finish",
+                    'P' => "Panel",
+                    _ => continue,
+                };
+                let mut entity_instance = EntityInstance {
+                    identifier: identifier.to_string(),
+                    iid: format!("{}_{}_{}", identifier, x, y),
+                    ..Default::default()
+                };
+                entity_instance.grid.x = x as i32;
+                entity_instance.grid.y = y as i32;
+                entities.entity_instances.push(entity_instance);
+            }
+        }
+        Level {
+            identifier: "Test".to_string(),
+            iid: "test".to_string(),
+            layer_instances: Some(vec![int_grid, entities]),
+            ..Default::default()
+        }
+    }
+
+    fn layer(identifier: &str, layer_instance_type: Type, rows: &[&str]) -> LayerInstance {
+        LayerInstance {
+            c_hei: rows.len() as i32,
+            c_wid: rows[0].len() as i32,
+            grid_size: 16,
+            identifier: identifier.to_string(),
+            opacity: 1.0,
+            px_total_offset_x: 0,
+            px_total_offset_y: 0,
+            tileset_def_uid: None,
+            tileset_rel_path: None,
+            layer_instance_type,
+            auto_layer_tiles: Vec::new(),
+            entity_instances: Vec::new(),
+            grid_tiles: Vec::new(),
+            iid: identifier.to_string(),
+            int_grid: None,
+            int_grid_csv: Vec::new(),
+            layer_def_uid: 0,
+            level_id: 0,
+            optional_rules: Vec::new(),
+            override_tileset_uid: None,
+            px_offset_x: 0,
+            px_offset_y: 0,
+            seed: 0,
+            visible: true,
+        }
+    }
+
+    fn field(identifier: &str, value: FieldValue) -> FieldInstance {
+        FieldInstance {
+            identifier: identifier.to_string(),
+            tile: None,
+            field_instance_type: String::new(),
+            value,
+            def_uid: 0,
+            real_editor_values: Vec::new(),
+        }
+    }
+
+    fn layer_mut<'a>(level: &'a mut Level, identifier: &str) -> &'a mut LayerInstance {
+        level
+            .layer_instances
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .find(|layer| layer.identifier == identifier)
+            .unwrap()
+    }
+
+    /// The first entity with `identifier` in the level.
+    fn entity_mut<'a>(level: &'a mut Level, identifier: &str) -> &'a mut EntityInstance {
+        layer_mut(level, "Entities")
+            .entity_instances
+            .iter_mut()
+            .find(|entity_instance| entity_instance.identifier == identifier)
+            .unwrap()
+    }
+
+    fn validate(level: &Level) -> LevelReport {
+        validate_level(level, &HashSet::from(["test"]))
+    }
+
+    #[test]
+    fn accepts_a_good_level() {
+        let report = validate(&level(&["@.F"]));
+        assert_eq!(report.issues, Vec::new());
+        assert_eq!(report.optimal, Some(2));
+    }
+
+    #[test]
+    fn reports_a_missing_player_and_finish() {
+        let report = validate(&level(&["..."]));
+        assert_eq!(
+            report.issues,
+            vec![LevelIssue::MissingPlayer, LevelIssue::MissingFinish]
+        );
+        assert_eq!(report.optimal, None);
+    }
+
+    #[test]
+    fn reports_invalid_int_grid_values() {
+        let mut level = level(&[
+            "@.F", //
+            "...", //
+        ]);
+        layer_mut(&mut level, "IntGrid").int_grid_csv[4] = 99;
+        let report = validate(&level);
+        assert_eq!(
+            report.issues,
+            vec![LevelIssue::InvalidIntGridValue {
+                x: 1,
+                y: 1,
+                value: 99
+            }]
+        );
+        assert_eq!(report.optimal, None);
+    }
+
+    #[test]
+    fn reports_unknown_door_colors() {
+        let mut level = level(&["@PF"]);
+        entity_mut(&mut level, "Panel")
+            .field_instances
+            .push(field("Door", FieldValue::Enum(Some("Purple".to_string()))));
+        assert_eq!(
+            validate(&level).issues,
+            vec![LevelIssue::InvalidDoor {
+                entity: "Panel".to_string(),
+                x: 1,
+                y: 0,
+                value: "Purple".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_multi_moves_without_a_count_for_every_direction() {
+        let mut level = level(&["@PF"]);
+        entity_mut(&mut level, "Panel").field_instances = vec![
+            field(
+                "Wasd_Multi_Move",
+                FieldValue::Enums(vec![Some("D".to_string()), Some("A".to_string())]),
+            ),
+            field("Multi_Move_Values", FieldValue::Ints(vec![Some(2)])),
+        ];
+        assert_eq!(
+            validate(&level).issues,
+            vec![LevelIssue::MultiMoveLengthMismatch {
+                x: 1,
+                y: 0,
+                directions: 2,
+                values: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_next_levels_missing_from_the_project() {
+        let mut level = level(&["@.F"]);
+        entity_mut(&mut level, "Finish").field_instances.push(field(
+            "NextLevel",
+            FieldValue::String(Some("gone".to_string())),
+        ));
+        assert_eq!(
+            validate(&level).issues,
+            vec![LevelIssue::UnresolvedNextLevel {
+                x: 2,
+                y: 0,
+                iid: "gone".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_finishes_and_still_solves_the_level() {
+        let report = validate(&level(&["F.@#F"]));
+        assert_eq!(
+            report.issues,
+            vec![LevelIssue::UnreachableFinish { x: 4, y: 0 }]
+        );
+        assert_eq!(report.optimal, Some(2));
+    }
+
+    #[test]
+    fn reports_a_par_below_the_best_solution() {
+        let mut level = level(&["@..F"]);
+        level
+            .field_instances
+            .push(field("Par", FieldValue::Int(Some(2))));
+        assert_eq!(
+            validate(&level).issues,
+            vec![LevelIssue::ParBelowOptimal { par: 2, optimal: 3 }]
+        );
+        level.field_instances[0].value = FieldValue::Int(Some(3));
+        assert_eq!(validate(&level).issues, Vec::new());
+    }
+}
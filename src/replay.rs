@@ -0,0 +1,312 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_ecs_ldtk::{LdtkAsset, LevelSelection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{
+        click_to_move::follow_click_target, input_buffer::release_input_buffer,
+        set_movement_actions, Actions,
+    },
+    levels::{CurrentPuzzle, PuzzleStepped},
+    loading::LevelAssets,
+    player::{
+        color_control::set_color_control_from_action,
+        death::Dying,
+        movement::{
+            next_movement_state, player_action_to_movement, MovementState, TweenTranslation,
+        },
+        undo::undo_redo_from_action,
+        Player,
+    },
+    save::load_save,
+    GameState,
+};
+
+const REPLAY_VERSION: u32 = 1;
+
+/// Records every [`Actions`] event into a replay file when the game is started with
+/// `--record <file>`, or plays one back instead of the keyboard with `--replay <file>`.
+pub struct ReplayPlugin;
+
+/// One [`Actions`] event and the point of the level it was sent at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedActions {
+    pub level_iid: String,
+    /// Puzzle steps taken since the level was (re)started, multi-move tiles included.
+    pub turn: usize,
+    /// The player was still moving or dying, so movement in `actions` had no effect.
+    pub player_busy: bool,
+    pub actions: Actions,
+}
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// IID of the level the recording started on, playback loads it first.
+    #[serde(default)]
+    pub start_level: Option<String>,
+    pub actions: Vec<RecordedActions>,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            start_level: None,
+            actions: Vec::new(),
+        }
+    }
+}
+
+impl Replay {
+    /// Replays from before the start level was kept begin where their first action was sent.
+    fn start_level(&self) -> Option<&String> {
+        self.start_level
+            .as_ref()
+            .or_else(|| self.actions.first().map(|recorded| &recorded.level_iid))
+    }
+}
+
+#[derive(Resource)]
+pub enum ReplayMode {
+    /// Input comes from the player and isn't kept.
+    Off,
+    Recording {
+        path: PathBuf,
+    },
+    Playback(PlaybackCursor),
+}
+
+#[derive(Default)]
+pub struct PlaybackCursor {
+    next: usize,
+    /// Set after a level reset was played back, until the level is rebuilt.
+    awaiting_level: bool,
+}
+
+/// Where the current level is at, in the terms of [`RecordedActions`].
+#[derive(Resource, Default)]
+pub struct ReplayClock {
+    level_iid: Option<String>,
+    turn: usize,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let mode_from_args = || match path_from_args("--record") {
+            Some(path) => ReplayMode::Recording { path },
+            None => ReplayMode::Off,
+        };
+        let (mode, replay) = match path_from_args("--replay") {
+            Some(path) => match load_replay(&path) {
+                Ok(replay) => {
+                    info!(
+                        "Playing back {} actions from {:?}",
+                        replay.actions.len(),
+                        path
+                    );
+                    (ReplayMode::Playback(PlaybackCursor::default()), replay)
+                }
+                Err(err) => {
+                    error!("Could not load replay {:?}: {}", path, err);
+                    (mode_from_args(), Replay::default())
+                }
+            },
+            None => (mode_from_args(), Replay::default()),
+        };
+        app.insert_resource(mode)
+            .insert_resource(replay)
+            .init_resource::<ReplayClock>()
+            .add_system(
+                select_start_level
+                    .after(load_save)
+                    .in_schedule(OnExit(GameState::Loading)),
+            )
+            .add_systems(
+                (
+                    count_turns.after(next_movement_state),
+                    record_actions.run_if(is_recording),
+                    play_back_actions.run_if(is_playing_back),
+                )
+                    .chain()
+                    .after(set_movement_actions)
+                    .after(follow_click_target)
+                    .after(release_input_buffer)
+                    .before(player_action_to_movement)
+                    .before(set_color_control_from_action)
+                    .before(undo_redo_from_action)
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(save_replay.in_base_set(CoreSet::Last));
+    }
+}
+
+/// Run condition for input sources that playback replaces.
+pub fn takes_player_input(mode: Res<ReplayMode>) -> bool {
+    !matches!(*mode, ReplayMode::Playback(_))
+}
+
+fn is_recording(mode: Res<ReplayMode>) -> bool {
+    matches!(*mode, ReplayMode::Recording { .. })
+}
+
+fn is_playing_back(mode: Res<ReplayMode>) -> bool {
+    !takes_player_input(mode)
+}
+
+fn path_from_args(flag: &str) -> Option<PathBuf> {
+    std::env::args()
+        .skip_while(|arg| arg != flag)
+        .nth(1)
+        .map(PathBuf::from)
+}
+
+fn load_replay(path: &Path) -> Result<Replay, String> {
+    let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let replay: Replay = serde_json::from_str(&contents).map_err(|err| err.to_string())?;
+    if replay.version != REPLAY_VERSION {
+        return Err(format!(
+            "unsupported replay version {}, expected {}",
+            replay.version, REPLAY_VERSION
+        ));
+    }
+    Ok(replay)
+}
+
+/// Playback starts on the level the replay was recorded on, whatever the save slot says. The
+/// loading state continues straight to [`GameState::Playing`] then, without the menu.
+fn select_start_level(
+    mode: Res<ReplayMode>,
+    replay: Res<Replay>,
+    mut level_selection: ResMut<LevelSelection>,
+    level_assets: Res<LevelAssets>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+) {
+    let ReplayMode::Playback(_) = *mode else { return; };
+    let Some(iid) = replay.start_level() else { return; };
+    let selection = LevelSelection::Iid(iid.clone());
+    let level_exists = ldtk_assets
+        .get(&level_assets.level)
+        .and_then(|ldtk| ldtk.get_level(&selection))
+        .is_some();
+    if level_exists {
+        *level_selection = selection;
+    } else {
+        warn!("Replay starts on level {}, which no longer exists", iid);
+    }
+}
+
+fn count_turns(
+    mut clock: ResMut<ReplayClock>,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    let steps = puzzle_stepped.iter().count();
+    if puzzle.is_added() {
+        clock.level_iid = Some(puzzle.layout().iid.clone());
+        clock.turn = 0;
+    } else {
+        clock.turn += steps;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn is_player_busy(
+    player_q: &Query<(&MovementState, Option<&TweenTranslation>, Option<&Dying>), With<Player>>,
+) -> bool {
+    player_q.iter().any(|(movement_state, tween, dying)| {
+        movement_state.is_moving() || tween.is_some() || dying.is_some()
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn record_actions(
+    mut actions: EventReader<Actions>,
+    mut replay: ResMut<Replay>,
+    clock: Res<ReplayClock>,
+    player_q: Query<(&MovementState, Option<&TweenTranslation>, Option<&Dying>), With<Player>>,
+) {
+    let Some(level_iid) = clock.level_iid.as_ref() else { return; };
+    if replay.start_level.is_none() {
+        replay.start_level = Some(level_iid.clone());
+    }
+    for actions in actions.iter() {
+        replay.actions.push(RecordedActions {
+            level_iid: level_iid.clone(),
+            turn: clock.turn,
+            player_busy: is_player_busy(&player_q),
+            actions: actions.clone(),
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn play_back_actions(
+    mut mode: ResMut<ReplayMode>,
+    mut actions: EventWriter<Actions>,
+    replay: Res<Replay>,
+    clock: Res<ReplayClock>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    player_q: Query<(&MovementState, Option<&TweenTranslation>, Option<&Dying>), With<Player>>,
+) {
+    let ReplayMode::Playback(cursor) = &mut *mode else { return; };
+    let Some(puzzle) = puzzle else { return; };
+    let Some(level_iid) = clock.level_iid.as_ref() else { return; };
+    if puzzle.is_added() {
+        cursor.awaiting_level = false;
+        // Whatever was left of the previous level can't change anything anymore.
+        while replay
+            .actions
+            .get(cursor.next)
+            .is_some_and(|recorded| recorded.level_iid != *level_iid)
+        {
+            cursor.next += 1;
+        }
+    }
+    if cursor.awaiting_level {
+        return;
+    }
+    let Some(recorded) = replay.actions.get(cursor.next) else { return; };
+    if recorded.level_iid != *level_iid || recorded.turn != clock.turn {
+        return;
+    }
+    if !recorded.player_busy && is_player_busy(&player_q) {
+        return;
+    }
+    actions.send(recorded.actions.clone());
+    cursor.awaiting_level = recorded.actions.level_reset.is_some();
+    cursor.next += 1;
+    if cursor.next == replay.actions.len() {
+        info!("Replay finished");
+    }
+}
+
+/// Writes the whole replay whenever a level is finished and on exit, next to the file
+/// first so a crash can't leave it half written.
+fn save_replay(
+    mode: Res<ReplayMode>,
+    replay: Res<Replay>,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    mut exit: EventReader<AppExit>,
+) {
+    let ReplayMode::Recording { path } = &*mode else { return; };
+    let level_finished = puzzle_stepped
+        .iter()
+        .any(|PuzzleStepped(outcome)| outcome.finished.is_some());
+    if !level_finished && exit.is_empty() {
+        return;
+    }
+    let result = serde_json::to_string(&*replay)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            let tmp_path = path.with_extension("json.tmp");
+            std::fs::write(&tmp_path, contents).map_err(|err| err.to_string())?;
+            std::fs::rename(&tmp_path, path).map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        warn!("Could not save replay to {:?}: {}", path, err);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{LdtkAsset, LevelSelection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::input_buffer::{InputBuffer, DEFAULT_INPUT_BUFFER_DEPTH},
+    audio::AudioConfig,
+    levels::{CurrentPuzzle, HintCounter, MoveCounter, PuzzleStepped},
+    loading::LevelAssets,
+    replay::takes_player_input,
+    GameState,
+};
+
+const SAVE_VERSION: u32 = 1;
+pub const SAVE_SLOTS: u32 = 3;
+
+/// Keeps progress between runs in one of [`SAVE_SLOTS`] files in the platform data directory.
+/// The slot is picked with `--slot <n>` and loaded when leaving [`GameState::Loading`], and
+/// can be switched from the level select screen.
+pub struct SavePlugin;
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveSlot(pub u32);
+
+impl SaveSlot {
+    fn from_args() -> Self {
+        let slot = std::env::args()
+            .skip_while(|arg| arg != "--slot")
+            .nth(1)
+            .and_then(|slot| slot.parse().ok())
+            .unwrap_or(0);
+        Self(slot.min(SAVE_SLOTS - 1))
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rgb-doors").join(format!("slot_{}.json", self.0)))
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub completed_levels: BTreeSet<String>,
+    /// Fewest moves each level was finished in, by level IID.
+    pub best_moves: BTreeMap<String, u32>,
+    /// Fewest hints each level was finished with, by level IID.
+    #[serde(default)]
+    pub fewest_hints: BTreeMap<String, u32>,
+    pub mute: bool,
+    #[serde(default = "default_input_buffer_depth")]
+    pub input_buffer_depth: usize,
+    /// IID of the level to continue from.
+    pub current_level: Option<String>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            completed_levels: BTreeSet::new(),
+            best_moves: BTreeMap::new(),
+            fewest_hints: BTreeMap::new(),
+            mute: false,
+            input_buffer_depth: DEFAULT_INPUT_BUFFER_DEPTH,
+            current_level: None,
+        }
+    }
+}
+
+fn default_input_buffer_depth() -> usize {
+    DEFAULT_INPUT_BUFFER_DEPTH
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveSlot::from_args())
+            .init_resource::<SaveData>()
+            .add_system(load_save.in_schedule(OnExit(GameState::Loading)))
+            .add_system(switch_save_slot.in_set(OnUpdate(GameState::Menu)))
+            .add_systems(
+                (
+                    record_progress.run_if(takes_player_input),
+                    sync_mute,
+                    sync_input_buffer_depth,
+                    write_save,
+                )
+                    .chain()
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+fn read_save(slot: SaveSlot) -> Result<Option<SaveData>, String> {
+    let Some(path) = slot.path() else { return Ok(None); };
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path).map_err(|err| err.to_string())?;
+    let save: SaveData = serde_json::from_str(&contents).map_err(|err| err.to_string())?;
+    if save.version != SAVE_VERSION {
+        return Err(format!(
+            "unsupported save version {}, expected {}",
+            save.version, SAVE_VERSION
+        ));
+    }
+    Ok(Some(save))
+}
+
+pub fn load_save(
+    mut commands: Commands,
+    slot: Res<SaveSlot>,
+    mut audio_config: ResMut<AudioConfig>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut level_selection: ResMut<LevelSelection>,
+    level_assets: Res<LevelAssets>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+) {
+    let save = match read_save(*slot) {
+        Ok(save) => save.unwrap_or_default(),
+        Err(err) => {
+            error!("Could not load save slot {}: {}", slot.0, err);
+            SaveData::default()
+        }
+    };
+    info!(
+        "Loaded save slot {} with {} completed levels",
+        slot.0,
+        save.completed_levels.len()
+    );
+    audio_config.mute = save.mute;
+    input_buffer.depth = save.input_buffer_depth;
+    if let Some(iid) = &save.current_level {
+        let selection = LevelSelection::Iid(iid.clone());
+        let level_exists = ldtk_assets
+            .get(&level_assets.level)
+            .and_then(|ldtk| ldtk.get_level(&selection))
+            .is_some();
+        if level_exists {
+            *level_selection = selection;
+        } else {
+            warn!("Saved level {} no longer exists", iid);
+        }
+    }
+    commands.insert_resource(save);
+}
+
+/// Loads the slot picked on the level select screen in place of the current one.
+fn switch_save_slot(
+    commands: Commands,
+    slot: Res<SaveSlot>,
+    audio_config: ResMut<AudioConfig>,
+    input_buffer: ResMut<InputBuffer>,
+    level_selection: ResMut<LevelSelection>,
+    level_assets: Res<LevelAssets>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+) {
+    if !slot.is_changed() || slot.is_added() {
+        return;
+    }
+    load_save(
+        commands,
+        slot,
+        audio_config,
+        input_buffer,
+        level_selection,
+        level_assets,
+        ldtk_assets,
+    );
+}
+
+fn record_progress(
+    mut save: ResMut<SaveData>,
+    mut puzzle_stepped: EventReader<PuzzleStepped>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    moves: Res<MoveCounter>,
+    hints: Res<HintCounter>,
+) {
+    let Some(puzzle) = puzzle else { return; };
+    let iid = &puzzle.layout().iid;
+    if save.current_level.as_ref() != Some(iid) {
+        save.current_level = Some(iid.clone());
+    }
+    for PuzzleStepped(outcome) in puzzle_stepped.iter() {
+        if outcome.finished.is_none() {
+            continue;
+        }
+        save.completed_levels.insert(iid.clone());
+        let best = save.best_moves.entry(iid.clone()).or_insert(**moves);
+        *best = (*best).min(**moves);
+        let fewest = save.fewest_hints.entry(iid.clone()).or_insert(hints.used);
+        *fewest = (*fewest).min(hints.used);
+    }
+}
+
+fn sync_mute(mut save: ResMut<SaveData>, audio_config: Res<AudioConfig>) {
+    if audio_config.is_changed() && save.mute != audio_config.mute {
+        save.mute = audio_config.mute;
+    }
+}
+
+fn sync_input_buffer_depth(mut save: ResMut<SaveData>, input_buffer: Res<InputBuffer>) {
+    if input_buffer.is_changed() && save.input_buffer_depth != input_buffer.depth {
+        save.input_buffer_depth = input_buffer.depth;
+    }
+}
+
+fn write_save(save: Res<SaveData>, slot: Res<SaveSlot>) {
+    if !save.is_changed() || save.is_added() {
+        return;
+    }
+    let Some(path) = slot.path() else { return; };
+    let result = serde_json::to_string_pretty(&*save)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+            }
+            // Write next to the save first so a crash can't leave it half written.
+            let tmp_path = path.with_extension("json.tmp");
+            std::fs::write(&tmp_path, contents).map_err(|err| err.to_string())?;
+            std::fs::rename(&tmp_path, &path).map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        warn!("Could not write save slot {}: {}", slot.0, err);
+    }
+}
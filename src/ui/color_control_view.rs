@@ -1,13 +1,24 @@
 use bevy::prelude::{ChildBuilder, *};
 
-use crate::player::color_control::ColorControl;
+use crate::{
+    actions::{click_to_move::ClickTarget, input_buffer::InputBuffer, Actions},
+    levels::CurrentPuzzle,
+    player::color_control::ColorControl,
+};
 
 use super::buttons_styles::BUTTON_DEFAULT_BG_COLOR;
 
 #[derive(Component)]
 pub struct ColorControlView;
 
-pub fn add_color_control(parent: &mut ChildBuilder, text_style: &TextStyle) {
+/// One of the level's colors inside [`ColorControlView`].
+#[derive(Component)]
+pub struct ColorSwatch(ColorControl);
+
+const SWATCH_SIZE: f32 = 28.;
+const SWATCH_INACTIVE_SIZE: f32 = 16.;
+
+pub fn add_color_control(parent: &mut ChildBuilder) {
     parent
         .spawn(ButtonBundle {
             style: Style {
@@ -15,50 +26,95 @@ pub fn add_color_control(parent: &mut ChildBuilder, text_style: &TextStyle) {
                 margin: UiRect::all(Val::Auto),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Row,
                 ..default()
             },
             background_color: BUTTON_DEFAULT_BG_COLOR.into(),
             ..default()
         })
-        .insert(ColorControlView)
-        .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                "",
-                TextStyle {
-                    color: Color::rgb_u8(21, 18, 23),
-                    ..text_style.clone()
-                },
-            ));
-        });
+        .insert(ColorControlView);
 }
 
+/// Queues a color switch like the keyboard does, so it keeps its place among buffered input.
 pub fn switch_red_or_blue_door_ignore_on_color_control_interaction(
     color_control_view_q: Query<&Interaction, (Changed<Interaction>, With<ColorControlView>)>,
-    mut color_control_q: Query<&mut ColorControl>,
+    mut click_target: ResMut<ClickTarget>,
+    mut input_buffer: ResMut<InputBuffer>,
 ) {
     for interaction in color_control_view_q.iter() {
         if *interaction == Interaction::Clicked {
-            for mut color_control in color_control_q.iter_mut() {
-                color_control.switch();
-            }
+            click_target.0 = None;
+            input_buffer.push(Actions {
+                color_switch: Some(()),
+                ..default()
+            });
         }
     }
 }
 
-pub fn change_button_text_on_color_control_change(
-    color_control_q: Query<&ColorControl, Changed<ColorControl>>,
-    color_control_view_q: Query<&Children, With<ColorControlView>>,
-    mut text_q: Query<&mut Text>,
+/// Shows every color of the level, the current one larger, rebuilt when the level changes.
+pub fn update_color_control_view(
+    mut commands: Commands,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    color_control_view_q: Query<(Entity, Option<&Children>), With<ColorControlView>>,
+    mut swatch_q: Query<(&ColorSwatch, &mut Style, &mut BackgroundColor)>,
 ) {
-    for color_control in color_control_q.iter() {
-        let text_str = match color_control {
-            ColorControl::Red => "Red",
-            ColorControl::Blue => "Blue",
-        };
-        for child in color_control_view_q.iter().flatten() {
-            if let Ok(mut text) = text_q.get_mut(*child) {
-                text.sections[0].value = text_str.to_string();
+    let Some(puzzle) = puzzle else { return; };
+    if !puzzle.is_changed() {
+        return;
+    }
+    let colors = &puzzle.layout().colors;
+    for (view, children) in color_control_view_q.iter() {
+        let swatches: &[Entity] = children.map(|children| &**children).unwrap_or(&[]);
+        let shown: Vec<ColorControl> = swatches
+            .iter()
+            .filter_map(|child| swatch_q.get(*child).ok())
+            .map(|(swatch, _, _)| swatch.0)
+            .collect();
+        if shown != *colors {
+            commands
+                .entity(view)
+                .despawn_descendants()
+                .with_children(|parent| {
+                    for color in colors.iter() {
+                        let (style, background_color) = swatch_look(*color, puzzle.color);
+                        parent
+                            .spawn(NodeBundle {
+                                style,
+                                background_color,
+                                ..default()
+                            })
+                            .insert(ColorSwatch(*color));
+                    }
+                });
+            continue;
+        }
+        for child in swatches.iter() {
+            if let Ok((swatch, mut style, mut background_color)) = swatch_q.get_mut(*child) {
+                (*style, *background_color) = swatch_look(swatch.0, puzzle.color);
             }
         }
     }
 }
+
+fn swatch_look(color: ColorControl, current: ColorControl) -> (Style, BackgroundColor) {
+    let (size, alpha) = if color == current {
+        (SWATCH_SIZE, 1.)
+    } else {
+        (SWATCH_INACTIVE_SIZE, 0.6)
+    };
+    let style = Style {
+        size: Size::new(Val::Px(size), Val::Px(size)),
+        margin: UiRect::all(Val::Px(3.)),
+        ..default()
+    };
+    (style, swatch_color(color).with_a(alpha).into())
+}
+
+pub fn swatch_color(color: ColorControl) -> Color {
+    match color {
+        ColorControl::Red => Color::rgb(221. / 255., 55. / 255., 69. / 255.),
+        ColorControl::Green => Color::rgb(66. / 255., 188. / 255., 127. / 255.),
+        ColorControl::Blue => Color::rgb(53. / 255., 74. / 255., 178. / 255.),
+    }
+}
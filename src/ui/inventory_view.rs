@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::player::{inventory::Inventory, Player};
+
+use super::color_control_view::swatch_color;
+
+#[derive(Component)]
+pub struct InventoryView;
+
+const KEY_SIZE: f32 = 12.;
+
+pub fn add_inventory_view(parent: &mut ChildBuilder) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(120.), Val::Px(24.)),
+                margin: UiRect::all(Val::Auto),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(InventoryView);
+}
+
+/// Shows one small swatch per key the player holds.
+pub fn update_inventory_view(
+    mut commands: Commands,
+    inventory_q: Query<&Inventory, (With<Player>, Changed<Inventory>)>,
+    inventory_view_q: Query<Entity, With<InventoryView>>,
+) {
+    let Some(inventory) = inventory_q.iter().next() else { return; };
+    for view in inventory_view_q.iter() {
+        commands
+            .entity(view)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for color in inventory.0.iter() {
+                    parent.spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(KEY_SIZE), Val::Px(KEY_SIZE)),
+                            margin: UiRect::all(Val::Px(2.)),
+                            ..default()
+                        },
+                        background_color: swatch_color(*color).into(),
+                        ..default()
+                    });
+                }
+            });
+    }
+}
@@ -2,18 +2,27 @@ pub mod bg_color_tween;
 pub mod buttons_styles;
 pub mod color_control_view;
 pub mod debug;
+pub mod inventory_view;
+pub mod move_counter;
 pub mod notifications;
 pub mod wasd;
 
 use bevy::prelude::*;
 
-use crate::{actions::MovementDirection, loading::FontAssets, GameState};
+use crate::{
+    actions::{bindings::Bindings, MovementDirection},
+    loading::FontAssets,
+    replay::takes_player_input,
+    GameState,
+};
 
 use self::{
     bg_color_tween::*,
     buttons_styles::style_button_interactions,
     color_control_view::*,
     debug::{add_debug_button, toggle_int_grid},
+    inventory_view::{add_inventory_view, update_inventory_view},
+    move_counter::{add_move_counter, update_move_counter},
     notifications::*,
     wasd::*,
 };
@@ -25,18 +34,24 @@ impl Plugin for UIPlugin {
         app.add_event::<Notification>()
             .add_event::<CleanNotificationQueue>()
             .add_system(spawn_game_ui.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(despawn_game_ui.in_schedule(OnExit(GameState::Playing)))
             .add_systems(
                 (
                     style_wasd_on_player_movement_action,
                     tween_background_color.after(style_wasd_on_player_movement_action),
                     style_button_interactions,
-                    switch_red_or_blue_door_ignore_on_color_control_interaction,
+                    switch_red_or_blue_door_ignore_on_color_control_interaction
+                        .run_if(takes_player_input),
                     update_notifications,
                     display_notifications,
                     toggle_int_grid,
                     clean_notifications.before(display_notifications),
-                    change_button_text_on_color_control_change,
+                    update_color_control_view,
                     set_wasd_forbidden,
+                    relabel_wasd_on_rebind,
+                    highlight_hinted_wasd,
+                    update_move_counter,
+                    update_inventory_view,
                 )
                     .in_set(OnUpdate(GameState::Playing)),
             );
@@ -49,7 +64,11 @@ pub struct LevelScreen;
 #[derive(Component)]
 pub struct RootUI;
 
-pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
+pub fn spawn_game_ui(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    bindings: Res<Bindings>,
+) {
     let text_style = TextStyle {
         font: font_assets.fira_sans.clone_weak(),
         color: Color::rgb_u8(21, 18, 23),
@@ -99,7 +118,8 @@ pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            add_color_control(parent, &text_style);
+                            add_color_control(parent);
+                            add_inventory_view(parent);
                             #[cfg(debug_assertions)]
                             {
                                 add_debug_button(parent, &text_style);
@@ -107,6 +127,7 @@ pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             add_mute_button(parent, &text_style);
                         });
                     add_notifications_ui(parent, &text_style);
+                    add_move_counter(parent, &text_style);
                     let wasd_size = 42.;
                     let wasd_margin = 8.;
                     let wasd_node_style = Style {
@@ -134,7 +155,13 @@ pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            add_wasd(parent, &wasd_node_style, &text_style, MovementDirection::Up);
+                            add_wasd(
+                                parent,
+                                &wasd_node_style,
+                                &text_style,
+                                &bindings,
+                                MovementDirection::Up,
+                            );
                             parent
                                 .spawn(NodeBundle {
                                     style: Style {
@@ -155,18 +182,21 @@ pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                                         parent,
                                         &wasd_node_style,
                                         &text_style,
+                                        &bindings,
                                         MovementDirection::Left,
                                     );
                                     add_wasd(
                                         parent,
                                         &wasd_node_style,
                                         &text_style,
+                                        &bindings,
                                         MovementDirection::Down,
                                     );
                                     add_wasd(
                                         parent,
                                         &wasd_node_style,
                                         &text_style,
+                                        &bindings,
                                         MovementDirection::Right,
                                     );
                                 });
@@ -176,6 +206,12 @@ pub fn spawn_game_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
         .insert(RootUI);
 }
 
+fn despawn_game_ui(mut commands: Commands, root_q: Query<Entity, With<RootUI>>) {
+    for entity in root_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 #[derive(Component)]
 pub struct MuteControl;
 
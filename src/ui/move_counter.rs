@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+use crate::levels::{CurrentPuzzle, MoveCounter};
+
+#[derive(Component)]
+pub struct MoveCounterText;
+
+pub fn add_move_counter(parent: &mut ChildBuilder, text_style: &TextStyle) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(120.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            color: Color::WHITE,
+                            ..text_style.clone()
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::Center),
+                )
+                .insert(MoveCounterText);
+        });
+}
+
+pub fn update_move_counter(
+    moves: Res<MoveCounter>,
+    puzzle: Option<Res<CurrentPuzzle>>,
+    mut text_q: Query<&mut Text, With<MoveCounterText>>,
+) {
+    let par = puzzle.as_ref().and_then(|puzzle| puzzle.layout().par);
+    let puzzle_changed = puzzle.as_ref().is_some_and(|puzzle| puzzle.is_changed());
+    if !moves.is_changed() && !puzzle_changed {
+        return;
+    }
+    for mut text in text_q.iter_mut() {
+        text.sections[0].value = match par {
+            Some(par) => format!("Moves\n{} / {}", **moves, par),
+            None => format!("Moves\n{}", **moves),
+        };
+    }
+}
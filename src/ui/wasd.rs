@@ -3,11 +3,13 @@ use std::collections::HashSet;
 use bevy::prelude::*;
 
 use crate::{
-    actions::{Actions, MovementDirection},
+    actions::{bindings::Bindings, game_control::GameControl, Actions, MovementDirection},
+    levels::hints::HintShown,
     player::movement_effects::{
         MovementSideEffects,
         SideEffect::{DisabledMovement, MultiMove, None},
     },
+    puzzle::PuzzleAction,
 };
 
 use super::bg_color_tween::BackgroundColorTween;
@@ -19,13 +21,8 @@ pub struct Wasd {
 }
 
 impl Wasd {
-    fn text(&self) -> String {
-        match self.player_movement {
-            MovementDirection::Up => "W".to_string(),
-            MovementDirection::Down => "S".to_string(),
-            MovementDirection::Left => "A".to_string(),
-            MovementDirection::Right => "D".to_string(),
-        }
+    fn text(&self, bindings: &Bindings) -> String {
+        bindings.label(GameControl::from(self.player_movement))
     }
 }
 
@@ -42,11 +39,13 @@ const WASD_DEFAULT_COLOR: Color = Color::rgb(131. / 255., 117. / 255., 131. / 25
 const WASD_PRESSED_COLOR: Color = Color::rgb(232. / 255., 219. / 255., 216. / 255.);
 const WASD_FORBID_COLOR: Color = Color::rgb(221. / 255., 55. / 255., 69. / 255.);
 const WASD_MULTIMOVE_COLOR: Color = Color::rgb(53. / 255., 74. / 255., 178. / 255.);
+const WASD_HINT_COLOR: Color = Color::rgb(96. / 255., 186. / 255., 84. / 255.);
 
 pub fn add_wasd(
     parent: &mut ChildBuilder,
     node_style: &Style,
     text_style: &TextStyle,
+    bindings: &Bindings,
     movement: MovementDirection,
 ) {
     let wasd = Wasd::from(movement);
@@ -59,7 +58,10 @@ pub fn add_wasd(
         })
         .insert(wasd.clone())
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(wasd.text(), text_style.clone()));
+            parent.spawn(TextBundle::from_section(
+                wasd.text(bindings),
+                text_style.clone(),
+            ));
         });
 }
 
@@ -90,6 +92,7 @@ pub fn set_wasd_forbidden(
     mut wasd_query: Query<(Entity, &mut BackgroundColor, &mut Wasd, &Children)>,
     mut text_q: Query<&mut Text>,
     side_effects_q: Query<&MovementSideEffects, Changed<MovementSideEffects>>,
+    bindings: Res<Bindings>,
 ) {
     for side_effects in side_effects_q.iter() {
         for (entity, mut background_color, mut wasd, children) in wasd_query.iter_mut() {
@@ -100,7 +103,7 @@ pub fn set_wasd_forbidden(
                     background_color.0 = WASD_DEFAULT_COLOR;
                     for child in children.iter() {
                         if let Ok(mut text) = text_q.get_mut(*child) {
-                            text.sections[0].value = wasd.text();
+                            text.sections[0].value = wasd.text(&bindings);
                         }
                     }
                 }
@@ -115,7 +118,7 @@ pub fn set_wasd_forbidden(
                     });
                     for child in children.iter() {
                         if let Ok(mut text) = text_q.get_mut(*child) {
-                            text.sections[0].value = wasd.text();
+                            text.sections[0].value = wasd.text(&bindings);
                         }
                     }
                 }
@@ -138,3 +141,49 @@ pub fn set_wasd_forbidden(
         }
     }
 }
+
+pub fn relabel_wasd_on_rebind(
+    bindings: Res<Bindings>,
+    wasd_query: Query<(&Wasd, &Children)>,
+    mut text_q: Query<&mut Text>,
+    side_effects_q: Query<&MovementSideEffects>,
+) {
+    if !bindings.is_changed() || bindings.is_added() {
+        return;
+    }
+    for (wasd, children) in wasd_query.iter() {
+        // Multi move tiles show the move count instead of the key.
+        let multi_move = side_effects_q
+            .iter()
+            .any(|side_effects| matches!(side_effects.get(wasd.player_movement), MultiMove(_)));
+        if multi_move {
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(*child) {
+                text.sections[0].value = wasd.text(&bindings);
+            }
+        }
+    }
+}
+
+pub fn highlight_hinted_wasd(
+    mut commands: Commands,
+    wasd_query: Query<(Entity, &BackgroundColor, &Wasd)>,
+    mut hint_shown: EventReader<HintShown>,
+) {
+    for HintShown(action) in hint_shown.iter() {
+        let PuzzleAction::Move(direction) = action else { continue; };
+        for (entity, background_color, wasd) in wasd_query.iter() {
+            if wasd.player_movement == *direction {
+                commands.entity(entity).insert(BackgroundColorTween {
+                    start_color: WASD_HINT_COLOR,
+                    end_color: background_color.0,
+                    after_color: background_color.0,
+                    duration: 1.5,
+                    elapsed: 0.0,
+                });
+            }
+        }
+    }
+}